@@ -1,260 +1,5721 @@
 // Schema Registry Implementation
 // 生產級別的 Schema 管理系統
+//
+// 構建: 本文件為單一範例, 放入 crate 後需宣告以下依賴與 features
+// (未啟用的 feature 其程式碼不會被編譯, 如 `otel` 的 span 包裝):
+//
+//     [dependencies]
+//     jsonschema = "0.17"
+//     serde_json = "1"
+//     notify = { version = "4", optional = true }
+//     serde_yaml = { version = "0.9", optional = true }
+//     json5 = { version = "0.4", optional = true }
+//     opentelemetry = { version = "0.27", optional = true }
+//     tokio = { version = "1", features = ["sync", "rt"], optional = true }
+//     rayon = { version = "1", optional = true }
+//
+//     [features]
+//     watch = ["dep:notify"]        # HotReloadRegistry::start_watching
+//     yaml = ["dep:serde_yaml"]     # 載入 .yaml/.yml schema
+//     json5 = ["dep:json5"]         # 載入 .json5 schema
+//     otel = ["dep:opentelemetry"]  # validate 的 OpenTelemetry span
+//     async = ["dep:tokio"]         # ConcurrencyLimitedRegistry::validate_async
+//     rayon = ["dep:rayon"]         # SchemaRegistry::validate_fields_par
+//
+// 測試: `cargo test --features watch,yaml,json5,otel,async,rayon`
 
-use jsonschema::{JSONSchema, ValidationError};
+use jsonschema::{CompilationOptions, Draft, JSONSchema, ValidationError, ValidationErrorKind};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt;
 use std::fs;
-use std::path::Path;
-use std::sync::{Arc, RwLock};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
 
 pub type ValidationResult = Result<(), Vec<String>>;
 
-/// Schema Registry - 管理所有 API Schemas
-pub struct SchemaRegistry {
-    schemas: HashMap<String, JSONSchema>,
-    schema_sources: HashMap<String, Value>,
+/// 結構化錯誤的驗證結果
+type DetailedResult = Result<(), Vec<ValidationErrorDetail>>;
+
+/// 單次驗證的成本統計 (用於計量計費與配額)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationStats {
+    /// 數據文件的節點數 (物件、陣列與純量), 作為驗證工作量的近似值
+    pub nodes_visited: usize,
+    pub errors_found: usize,
+    pub duration: Duration,
 }
 
-impl SchemaRegistry {
-    /// 從目錄載入所有 schemas
-    pub fn from_directory<P: AsRef<Path>>(path: P) -> Result<Self, std::io::Error> {
-        let mut registry = Self {
-            schemas: HashMap::new(),
-            schema_sources: HashMap::new(),
-        };
+/// `validate_data_directory` 的結果
+#[derive(Debug, Default)]
+pub struct DataDirectoryReport {
+    pub results: HashMap<PathBuf, ValidationResult>,
+    /// 無法推導出 schema 名稱的文件
+    pub unmatched: Vec<PathBuf>,
+}
 
-        registry.load_schemas(path)?;
-        Ok(registry)
+/// `validate_observable` 推送的事件
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationEvent {
+    Started { schema_name: String },
+    Error(ValidationErrorDetail),
+    Finished { error_count: usize },
+}
+
+/// `validate_with_fallback` 的結果: 最終由哪個 schema 接受數據
+#[derive(Debug, Clone, PartialEq)]
+pub enum FallbackResult {
+    /// 主 schema 接受數據
+    Primary,
+    /// 主 schema 拒絕, 由後備 (legacy) schema 接受
+    Fallback { primary_errors: Vec<String> },
+    /// 兩者皆拒絕
+    Rejected { primary_errors: Vec<String>, fallback_errors: Vec<String> },
+}
+
+impl FallbackResult {
+    /// 任一 schema 接受數據時為 true
+    pub fn is_accepted(&self) -> bool {
+        !matches!(self, FallbackResult::Rejected { .. })
     }
+}
 
-    /// 載入指定目錄的所有 JSON Schema 文件
-    fn load_schemas<P: AsRef<Path>>(&mut self, path: P) -> Result<(), std::io::Error> {
-        for entry in fs::read_dir(path)? {
-            let entry = entry?;
-            let path = entry.path();
+/// 驗證未在時限內完成
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedOut {
+    pub limit: Duration,
+}
 
-            if path.extension().and_then(|s| s.to_str()) == Some("json") {
-                let name = path
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("unknown")
-                    .to_string();
-
-                let schema_str = fs::read_to_string(&path)?;
-                let schema: Value = serde_json::from_str(&schema_str)
-                    .map_err(|e| std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        format!("Invalid JSON in {}: {}", name, e)
-                    ))?;
-
-                match JSONSchema::compile(&schema) {
-                    Ok(compiled) => {
-                        println!("✅ Loaded schema: {}", name);
-                        self.schema_sources.insert(name.clone(), schema);
-                        self.schemas.insert(name, compiled);
-                    }
-                    Err(e) => {
-                        eprintln!("❌ Failed to compile schema {}: {}", name, e);
-                    }
-                }
-            }
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Validation did not finish within {:?}", self.limit)
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// `validate_with_deadline` 沒有返回驗證結果的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadlineError {
+    /// 驗證未在時限內完成
+    TimedOut(TimedOut),
+    /// 背景中的超時工作線程已達上限, 未開始驗證; 稍後重試或改用 `validate`
+    Overloaded(Overloaded),
+}
+
+impl fmt::Display for DeadlineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeadlineError::TimedOut(e) => write!(f, "{}", e),
+            DeadlineError::Overloaded(e) => write!(f, "{} (abandoned deadline workers)", e),
         }
+    }
+}
 
-        println!("📊 Total schemas loaded: {}", self.schemas.len());
-        Ok(())
+impl std::error::Error for DeadlineError {}
+
+/// 並發驗證已達上限 (`OverflowPolicy::Reject` 模式)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overloaded {
+    pub limit: usize,
+}
+
+impl fmt::Display for Overloaded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Concurrency limit of {} validations reached", self.limit)
     }
+}
 
-    /// 驗證數據是否符合指定 schema
-    pub fn validate(&self, schema_name: &str, data: &Value) -> ValidationResult {
-        let schema = self.schemas.get(schema_name)
-            .ok_or_else(|| vec![format!("Schema '{}' not found", schema_name)])?;
+impl std::error::Error for Overloaded {}
 
-        if schema.is_valid(data) {
-            Ok(())
-        } else {
-            let errors = schema
-                .iter_errors(data)
-                .map(|e| format!("{} at {}", e, e.instance_path()))
-                .collect();
-            Err(errors)
+/// 結構驗證通過後執行的跨欄位規則 (如 `end_date` 必須晚於 `start_date`)
+pub type CrossFieldRule = Arc<dyn Fn(&Value) -> Result<(), String> + Send + Sync>;
+
+/// `with_switch` 遇到未對應的欄位值 (或欄位缺失) 時的行為
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownSwitchValue {
+    /// 返回驗證錯誤
+    Error,
+    /// 只以原 schema 驗證
+    FallThrough,
+}
+
+/// 依數據欄位值選擇附加子 schema 的設定 (見 `SchemaRegistry::with_switch`)
+struct SchemaSwitch {
+    field: String,
+    cases: HashMap<String, Value>,
+    on_unknown: UnknownSwitchValue,
+}
+
+/// 自定義 `format` 驗證函數
+pub type FormatValidator = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// 結構化的驗證錯誤
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationErrorDetail {
+    /// 出錯數據的 JSON Pointer (如 `/address/city`)
+    pub instance_path: String,
+    /// 出錯 keyword 在 schema 中的 JSON Pointer
+    pub schema_path: String,
+    /// 失敗的 keyword (`required`, `type`, `pattern`...)
+    pub keyword: String,
+    pub message: String,
+    /// 產生此錯誤的 schema 版本雜湊 (見 `ValidateOptions::include_schema_hash`)
+    pub schema_hash: Option<String>,
+    /// `enum` 失敗時編輯距離最接近的允許值 (如提交 `activ` 時為 `active`)
+    pub suggestion: Option<String>,
+}
+
+impl ValidationErrorDetail {
+    fn from_error(error: &ValidationError) -> Self {
+        let schema_path = error.schema_path().to_string();
+        let keyword = schema_path.rsplit('/').next().unwrap_or_default().to_string();
+        Self {
+            instance_path: error.instance_path().to_string(),
+            schema_path,
+            keyword,
+            message: error.to_string(),
+            schema_hash: None,
+            suggestion: match error.kind() {
+                ValidationErrorKind::Enum { options } => suggest_enum_value(options, error.instance()),
+                _ => None,
+            },
         }
     }
 
-    /// 快速檢查數據是否有效 (不返回詳細錯誤)
-    pub fn is_valid(&self, schema_name: &str, data: &Value) -> bool {
-        self.schemas
-            .get(schema_name)
-            .map(|schema| schema.is_valid(data))
-            .unwrap_or(false)
+    /// 錯誤的 JSON 表示 (用於回應給客戶端)
+    pub fn to_json(&self) -> Value {
+        let mut json = serde_json::json!({
+            "instance_path": self.instance_path,
+            "schema_path": self.schema_path,
+            "keyword": self.keyword,
+            "message": self.message,
+        });
+        if let Some(hash) = &self.schema_hash {
+            json["schema_hash"] = Value::String(hash.clone());
+        }
+        if let Some(suggestion) = &self.suggestion {
+            json["suggestion"] = Value::String(suggestion.clone());
+        }
+        json
     }
 
-    /// 獲取所有已載入的 schema 名稱
-    pub fn list_schemas(&self) -> Vec<&String> {
-        self.schemas.keys().collect()
+    fn truncated(max_bytes: usize) -> Self {
+        Self {
+            instance_path: String::new(),
+            schema_path: String::new(),
+            keyword: "truncated".to_string(),
+            message: format!("Further errors omitted: payload exceeds {} bytes", max_bytes),
+            schema_hash: None,
+            suggestion: None,
+        }
     }
 
-    /// 獲取 schema 的源 JSON (用於文檔生成)
-    pub fn get_schema_source(&self, name: &str) -> Option<&Value> {
-        self.schema_sources.get(name)
+    /// 跨欄位規則的失敗
+    fn cross_field(message: String) -> Self {
+        Self {
+            instance_path: String::new(),
+            schema_path: String::new(),
+            keyword: "crossField".to_string(),
+            message,
+            schema_hash: None,
+            suggestion: None,
+        }
+    }
+
+    fn schema_not_found(schema_name: &str) -> Self {
+        Self::schema_error(format!("Schema '{}' not found", schema_name))
+    }
+
+    /// 與數據無關的 schema 層級錯誤 (找不到、變體編譯失敗...)
+    fn schema_error(message: String) -> Self {
+        Self {
+            instance_path: String::new(),
+            schema_path: String::new(),
+            keyword: "schema".to_string(),
+            message,
+            schema_hash: None,
+            suggestion: None,
+        }
     }
 }
 
-/// 支持多版本的 Schema Registry
-pub struct VersionedRegistry {
-    versions: HashMap<String, SchemaRegistry>,
-    default_version: String,
+impl fmt::Display for ValidationErrorDetail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.instance_path)
+    }
 }
 
-impl VersionedRegistry {
-    /// 創建多版本 registry
-    pub fn new(base_path: &str, versions: &[&str]) -> Result<Self, std::io::Error> {
-        let mut registries = HashMap::new();
+/// 實現 `std::error::Error` 的驗證錯誤, 可配合 `?`、`anyhow`、`thiserror` 使用
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaValidationError {
+    pub schema_name: String,
+    pub errors: Vec<ValidationErrorDetail>,
+}
 
-        for version in versions {
-            let path = format!("{}/{}", base_path, version);
-            match SchemaRegistry::from_directory(&path) {
-                Ok(registry) => {
-                    println!("📦 Loaded version: {}", version);
-                    registries.insert(version.to_string(), registry);
-                }
-                Err(e) => {
-                    eprintln!("⚠️  Failed to load version {}: {}", version, e);
-                }
-            }
+impl fmt::Display for SchemaValidationError {
+    /// 摘要錯誤數量與前幾條訊息
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const SHOWN: usize = 3;
+
+        write!(
+            f,
+            "{} validation error(s) for schema '{}'",
+            self.errors.len(),
+            self.schema_name
+        )?;
+        for (i, error) in self.errors.iter().take(SHOWN).enumerate() {
+            write!(f, "{}{}", if i == 0 { ": " } else { "; " }, error)?;
+        }
+        if self.errors.len() > SHOWN {
+            write!(f, "; and {} more", self.errors.len() - SHOWN)?;
         }
+        Ok(())
+    }
+}
 
-        let default_version = versions.last()
-            .ok_or_else(|| std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "At least one version required"
-            ))?
-            .to_string();
+impl std::error::Error for SchemaValidationError {}
 
-        Ok(Self {
-            versions: registries,
-            default_version,
-        })
-    }
+/// 日期欄位的日/月/年順序
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateOrder {
+    /// 2024-12-31 (ISO 8601)
+    Ymd,
+    /// 31/12/2024 (歐洲)
+    Dmy,
+    /// 12/31/2024 (美國)
+    Mdy,
+}
 
-    /// 使用指定版本驗證
-    pub fn validate(&self, version: Option<&str>, schema_name: &str, data: &Value) -> ValidationResult {
-        let version = version.unwrap_or(&self.default_version);
+/// 數字與日期的地區格式 (僅在 coercion 模式下使用)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    pub decimal_separator: char,
+    pub thousands_separator: Option<char>,
+    pub date_order: DateOrder,
+}
 
-        let registry = self.versions.get(version)
-            .ok_or_else(|| vec![format!("Version '{}' not found", version)])?;
+impl Locale {
+    /// 歐洲格式: `1.234,5` 與 `31/12/2024`
+    pub fn european() -> Self {
+        Self {
+            decimal_separator: ',',
+            thousands_separator: Some('.'),
+            date_order: DateOrder::Dmy,
+        }
+    }
 
-        registry.validate(schema_name, data)
+    /// 美國格式: `1,234.5` 與 `12/31/2024`
+    pub fn us() -> Self {
+        Self {
+            decimal_separator: '.',
+            thousands_separator: Some(','),
+            date_order: DateOrder::Mdy,
+        }
     }
+}
 
-    /// 列出所有版本
-    pub fn list_versions(&self) -> Vec<&String> {
-        self.versions.keys().collect()
+impl Default for Locale {
+    /// 標準 JSON 數字與 ISO 8601 日期
+    fn default() -> Self {
+        Self {
+            decimal_separator: '.',
+            thousands_separator: None,
+            date_order: DateOrder::Ymd,
+        }
     }
 }
 
-/// 支持熱重載的 Schema Registry
-pub struct HotReloadRegistry {
-    registry: Arc<RwLock<SchemaRegistry>>,
-    path: String,
+/// `validate_coerced` 的選項
+#[derive(Debug, Clone, Default)]
+pub struct CoerceOptions {
+    pub locale: Locale,
 }
 
-impl HotReloadRegistry {
-    /// 創建支持熱重載的 registry
-    pub fn new(path: &str) -> Result<Self, std::io::Error> {
-        let registry = Arc::new(RwLock::new(
-            SchemaRegistry::from_directory(path)?
-        ));
+/// `validate_with_options` 的選項
+#[derive(Debug, Clone, Default)]
+pub struct ValidateOptions {
+    /// 在每個錯誤中附上 `schema_hash`, 讓日誌能對應到確切的 schema 版本
+    pub include_schema_hash: bool,
+    /// 錯誤序列化後 (`to_json`) 的總大小上限; 超過時停止收集並附上 `truncated` 標記
+    ///
+    /// 與錯誤數量上限不同: 單一錯誤訊息本身就可能很大。標記本身不計入上限。
+    pub max_error_bytes: Option<usize>,
+    /// 任何字串值超過此位元組數時, 在完整驗證前直接拒絕並指出路徑
+    ///
+    /// 涵蓋沒有 `maxLength` 的欄位, 且比讓 jsonschema 逐一檢查更便宜。
+    pub max_string_bytes: Option<usize>,
+    /// 數值邊界的容差: 超出 `minimum`/`maximum`/`exclusiveMinimum`/`exclusiveMaximum`
+    /// 不超過 epsilon 的值仍視為通過 (用於帶浮點雜訊的感測器數據)
+    ///
+    /// 只影響這四個 keyword 的數值邊界; `multipleOf`、`const`、`enum` 等仍精確比較。
+    pub numeric_epsilon: Option<f64>,
+    /// 將值為 `null` 的屬性視為不存在 (影響 `required` 檢查)
+    ///
+    /// 會改變驗證結果的選項, 預設關閉: 開啟後 `{"email": null}` 對 `required: ["email"]`
+    /// 失敗, 而對 `type: "string"` 的選填欄位則會通過。
+    pub treat_null_as_missing: bool,
+    /// 依 `(instance_path, keyword, message)` 排序錯誤, 讓輸出跨執行與 jsonschema 版本穩定
+    ///
+    /// 預設保留 jsonschema 的原生順序。與 `max_error_bytes` 併用時,
+    /// 保留哪些錯誤仍取決於原生順序, 排序只作用於保留下來的錯誤。
+    pub sort_errors: bool,
+}
 
-        Ok(Self {
-            registry,
-            path: path.to_string(),
+/// `validate_normalize` 的選項
+#[derive(Debug, Clone)]
+pub struct NormalizeOptions {
+    /// 填入 schema 宣告的 `default` 值
+    pub apply_defaults: bool,
+    /// 移除 schema 未宣告的屬性
+    pub strip_unknown: bool,
+    /// 填入缺少的 `const`/單值 `enum` 屬性 (見 `constants`)
+    pub fill_constants: bool,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        Self {
+            apply_defaults: true,
+            strip_unknown: true,
+            fill_constants: false,
+        }
+    }
+}
+
+/// schema 欄位的覆蓋情況 (屬性路徑以點號分隔, 如 `address.city`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CoverageReport {
+    pub seen_properties: BTreeSet<String>,
+    pub unseen_properties: BTreeSet<String>,
+    /// enum 欄位路徑 -> 出現過的值 (JSON 字串形式)
+    pub seen_enum_values: BTreeMap<String, BTreeSet<String>>,
+    /// enum 欄位路徑 -> 從未出現的值 (JSON 字串形式)
+    pub unseen_enum_values: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// 單一 schema 累積看到的屬性與 enum 值
+#[derive(Debug, Default)]
+struct CoverageAccumulator {
+    properties: BTreeSet<String>,
+    enum_values: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// schema 文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Json,
+    /// 需要 `json5` feature
+    Json5,
+    /// 需要 `yaml` feature (`.yaml` 或 `.yml`)
+    Yaml,
+}
+
+impl Format {
+    /// 依副檔名判斷格式, 未啟用對應 feature 的格式返回 None
+    fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "json" => Some(Format::Json),
+            #[cfg(feature = "json5")]
+            "json5" => Some(Format::Json5),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Some(Format::Yaml),
+            _ => None,
+        }
+    }
+
+    fn parse(self, text: &str) -> Result<Value, String> {
+        match self {
+            Format::Json => serde_json::from_str(text).map_err(|e| e.to_string()),
+            #[cfg(feature = "json5")]
+            Format::Json5 => json5::from_str(text).map_err(|e| e.to_string()),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => serde_yaml::from_str(text).map_err(|e| e.to_string()),
+            #[allow(unreachable_patterns)]
+            other => Err(format!("{} support is not enabled", other)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Json => "JSON",
+            Format::Json5 => "JSON5",
+            Format::Yaml => "YAML",
         })
     }
+}
 
-    /// 手動重載 schemas
-    pub fn reload(&self) -> Result<(), std::io::Error> {
-        let new_registry = SchemaRegistry::from_directory(&self.path)?;
-        *self.registry.write().unwrap() = new_registry;
-        println!("🔄 Schemas reloaded from {}", self.path);
-        Ok(())
+/// 載入時、編譯前對 schema 源的轉換 (參數為 schema 名稱與可修改的源)
+pub type SourceTransform = Box<dyn Fn(&str, &mut Value) + Send + Sync>;
+
+/// SchemaRegistry 的載入選項
+pub struct RegistryBuilder {
+    format_precedence: Vec<Format>,
+    auto_draft: bool,
+    transforms: Vec<SourceTransform>,
+    permissive_unknown_schema: bool,
+    max_ref_depth: Option<usize>,
+    base_uri: Option<String>,
+}
+
+impl Default for RegistryBuilder {
+    fn default() -> Self {
+        Self {
+            format_precedence: vec![Format::Json, Format::Json5, Format::Yaml],
+            auto_draft: false,
+            transforms: Vec::new(),
+            permissive_unknown_schema: false,
+            max_ref_depth: None,
+            base_uri: None,
+        }
     }
+}
 
-    /// 驗證數據
-    pub fn validate(&self, schema_name: &str, data: &Value) -> ValidationResult {
-        self.registry.read().unwrap().validate(schema_name, data)
+impl RegistryBuilder {
+    /// 同名 schema 有多種格式時的採用順序 (預設 json > json5 > yaml)
+    ///
+    /// 未列出的格式不會被載入, 適合從 YAML 逐步遷移到 JSON 的目錄。
+    pub fn with_format_precedence(mut self, precedence: &[Format]) -> Self {
+        self.format_precedence = precedence.to_vec();
+        self
     }
 
-    /// 啟動自動監聽文件變化 (需要 notify crate)
-    #[cfg(feature = "watch")]
-    pub fn start_watching(&self) -> notify::Result<()> {
-        use notify::{Watcher, RecursiveMode, watcher};
-        use std::sync::mpsc::channel;
-        use std::time::Duration;
+    /// 以 `detect_draft` 逐一判斷每個文件的 draft 後編譯 (用於來源不明的第三方 schema)
+    pub fn auto_draft(mut self, enabled: bool) -> Self {
+        self.auto_draft = enabled;
+        self
+    }
 
-        let (tx, rx) = channel();
-        let mut watcher = watcher(tx, Duration::from_secs(1))?;
-        watcher.watch(&self.path, RecursiveMode::Recursive)?;
+    /// 註冊編譯前的源轉換 (如注入標準 `$id`、補上 `additionalProperties: false`)
+    ///
+    /// 多個轉換依註冊順序執行, 轉換後的源即為 `get_schema_source` 返回的內容。
+    pub fn with_source_transform(mut self, f: impl Fn(&str, &mut Value) + Send + Sync + 'static) -> Self {
+        self.transforms.push(Box::new(f));
+        self
+    }
 
-        let registry = Arc::clone(&self.registry);
-        let path = self.path.clone();
+    /// ⚠️ 危險: 驗證不存在的 schema 時返回 `Ok(())` (並輸出警告) 而非錯誤
+    ///
+    /// 僅用於逐步導入、尚非所有訊息類型都有 schema 的過渡期。開啟後拼錯的 schema
+    /// 名稱也會讓所有數據通過。影響所有只返回驗證結果的方法: `validate`、`validate_detailed`、
+    /// `validate_with_options`、`is_valid`、`validate_with_deadline` 與批次版本、`validate_observable`、
+    /// `validate_with_format_overrides`、`validate_with_extra_required`、`validate_with_enum_override`、
+    /// `validate_closed` 與 `validate_fields_par` (返回空結果); 返回轉換後數據的方法仍要求 schema 存在。
+    pub fn permissive_unknown_schema(mut self, enabled: bool) -> Self {
+        self.permissive_unknown_schema = enabled;
+        self
+    }
 
-        std::thread::spawn(move || {
-            loop {
-                match rx.recv() {
-                    Ok(_event) => {
-                        if let Ok(new_registry) = SchemaRegistry::from_directory(&path) {
-                            *registry.write().unwrap() = new_registry;
-                            println!("🔄 Auto-reloaded schemas");
-                        }
-                    }
-                    Err(e) => eprintln!("Watch error: {}", e),
-                }
-            }
-        });
+    /// 限制文件內 `$ref` 鏈 (引用目標本身又是 `$ref`) 的長度
+    ///
+    /// 超過限制或形成純引用循環的 schema 編譯失敗, 錯誤訊息列出整條鏈;
+    /// 用於編譯使用者提供的 schema 時防範惡意或錯誤的引用。只檢查 `#` 開頭的本地引用。
+    pub fn max_ref_depth(mut self, depth: usize) -> Self {
+        self.max_ref_depth = Some(depth);
+        self
+    }
 
-        Ok(())
+    /// 以共同 URI 命名空間編譯 schemas (如 `https://acme.com/schemas/`)
+    ///
+    /// 沒有 `$id` 的 schema 以 `<base_uri><名稱>` 作為 `$id`, 所有 schemas 依 `$id`
+    /// 互相註冊, 讓跨 schema 的 `$ref` 以 URI 而非文件名解析。
+    pub fn with_base_uri(mut self, base_uri: &str) -> Self {
+        self.base_uri = Some(base_uri.to_string());
+        self
+    }
+
+    /// 從目錄載入所有 schemas
+    pub fn load<P: AsRef<Path>>(&self, path: P) -> Result<SchemaRegistry, std::io::Error> {
+        let source = DirectorySource::new(path).with_format_precedence(&self.format_precedence);
+        self.load_source(&source).map_err(std::io::Error::from)
+    }
+
+    /// 從任意 `SchemaSource` 載入所有 schemas
+    pub fn load_source(&self, source: &dyn SchemaSource) -> Result<SchemaRegistry, SourceError> {
+        let mut registry = SchemaRegistry::empty();
+        registry.permissive_unknown_schema = self.permissive_unknown_schema;
+        registry.compile_sources(source.load()?, self);
+        Ok(registry)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
+/// schema 來源載入失敗
+#[derive(Debug)]
+pub enum SourceError {
+    /// 讀取來源時的 I/O 錯誤
+    Io(std::io::Error),
+    /// 來源內容無法解析為 schema
+    Invalid(String),
+    /// 自訂來源 (資料庫、S3、git...) 的其他錯誤
+    Other(Box<dyn std::error::Error + Send + Sync>),
+}
 
-    #[test]
-    fn test_schema_registry() {
-        let registry = SchemaRegistry::from_directory("./schemas/v1")
-            .expect("Failed to load schemas");
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceError::Io(e) => write!(f, "{}", e),
+            SourceError::Invalid(message) => write!(f, "{}", message),
+            SourceError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
 
-        // Valid data
-        let valid_user = json!({
-            "email": "alice@example.com",
-            "name": "Alice"
-        });
-        assert!(registry.validate("user", &valid_user).is_ok());
+impl std::error::Error for SourceError {}
 
-        // Invalid data
-        let invalid_user = json!({
-            "name": "Bob"  // Missing email
-        });
-        assert!(registry.validate("user", &invalid_user).is_err());
+impl From<std::io::Error> for SourceError {
+    fn from(e: std::io::Error) -> Self {
+        SourceError::Io(e)
     }
+}
 
-    #[test]
-    fn test_versioned_registry() {
-        let registry = VersionedRegistry::new("./schemas", &["v1", "v2"])
-            .expect("Failed to load versions");
+impl From<SourceError> for std::io::Error {
+    fn from(e: SourceError) -> Self {
+        match e {
+            SourceError::Io(e) => e,
+            SourceError::Invalid(message) => std::io::Error::new(std::io::ErrorKind::InvalidData, message),
+            SourceError::Other(e) => std::io::Error::other(e),
+        }
+    }
+}
 
-        let data = json!({"email": "test@example.com", "name": "Test"});
+/// schema 的來源, 返回 (名稱, schema 源) 列表供 registry 編譯
+///
+/// 實作此 trait 即可從資料庫、S3 或 git 載入, 並沿用所有編譯與驗證功能。
+pub trait SchemaSource {
+    /// 載入所有 schema 源
+    fn load(&self) -> Result<Vec<(String, Value)>, SourceError>;
+}
 
-        // Test v1
-        assert!(registry.validate(Some("v1"), "user", &data).is_ok());
+/// 從目錄載入 schema 文件的來源
+#[derive(Debug, Clone)]
+pub struct DirectorySource {
+    path: PathBuf,
+    format_precedence: Vec<Format>,
+}
 
-        // Test v2
-        assert!(registry.validate(Some("v2"), "user", &data).is_ok());
+impl DirectorySource {
+    /// 使用預設格式優先順序的目錄來源
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            format_precedence: RegistryBuilder::default().format_precedence,
+        }
+    }
 
-        // Test default version (v2)
-        assert!(registry.validate(None, "user", &data).is_ok());
+    /// 設定同名文件的格式優先順序 (見 `RegistryBuilder::with_format_precedence`)
+    pub fn with_format_precedence(mut self, precedence: &[Format]) -> Self {
+        self.format_precedence = precedence.to_vec();
+        self
+    }
+}
+
+impl SchemaSource for DirectorySource {
+    /// 同名的多個文件 (如 `user.json` 與 `user.yaml`) 依格式優先順序採用排序最前者,
+    /// 其餘記錄為被遮蔽; 不在優先順序中的格式不會被載入。
+    fn load(&self) -> Result<Vec<(String, Value)>, SourceError> {
+        let precedence = &self.format_precedence;
+        let mut candidates: BTreeMap<String, Vec<(Format, PathBuf)>> = BTreeMap::new();
+        for entry in fs::read_dir(&self.path)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            let format = path
+                .extension()
+                .and_then(|s| s.to_str())
+                .and_then(Format::from_extension);
+            let Some(format) = format.filter(|f| precedence.contains(f)) else {
+                continue;
+            };
+
+            let name = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("unknown")
+                .to_string();
+            candidates.entry(name).or_default().push((format, path));
+        }
+
+        let mut sources = Vec::new();
+        for (name, mut files) in candidates {
+            files.sort_by_key(|(format, _)| precedence.iter().position(|f| f == format));
+            let (format, path) = files.remove(0);
+            for (_, shadowed) in &files {
+                println!("⚠️  Shadowed schema file {} (using {})", shadowed.display(), path.display());
+            }
+
+            let schema_str = fs::read_to_string(&path)?;
+            let schema: Value = format.parse(&schema_str)
+                .map_err(|e| SourceError::Invalid(format!("Invalid {} in {}: {}", format, name, e)))?;
+            sources.push((name, schema));
+        }
+        Ok(sources)
+    }
+}
+
+/// `detect_draft` 依序嘗試的 drafts (新到舊)
+const SUPPORTED_DRAFTS: [Draft; 5] = [
+    Draft::Draft202012,
+    Draft::Draft201909,
+    Draft::Draft7,
+    Draft::Draft6,
+    Draft::Draft4,
+];
+
+/// 判斷 schema 使用的 draft
+///
+/// 優先依 `$schema` 判斷; 沒有 `$schema` 時依新到舊嘗試編譯, 返回第一個成功的 draft。
+pub fn detect_draft(source: &Value) -> Option<Draft> {
+    if let Some(draft) = declared_draft(source) {
+        return Some(draft);
+    }
+
+    SUPPORTED_DRAFTS
+        .into_iter()
+        .find(|draft| JSONSchema::options().with_draft(*draft).compile(source).is_ok())
+}
+
+/// schema 以 `$schema` 宣告的 draft
+fn declared_draft(source: &Value) -> Option<Draft> {
+    let uri = source.get("$schema").and_then(Value::as_str)?;
+    let declared = [
+        ("draft/2020-12", Draft::Draft202012),
+        ("draft/2019-09", Draft::Draft201909),
+        ("draft-07", Draft::Draft7),
+        ("draft-06", Draft::Draft6),
+        ("draft-04", Draft::Draft4),
+    ];
+    declared.iter().find(|(marker, _)| uri.contains(marker)).map(|(_, draft)| *draft)
+}
+
+/// schema 源的標準形式與其內容雜湊
+#[derive(Debug, Clone)]
+struct CanonicalForm {
+    source: Value,
+    hash: String,
+}
+
+/// Schema Registry - 管理所有 API Schemas
+pub struct SchemaRegistry {
+    /// 以 Arc 共享, 讓有時限的驗證能在工作線程中使用
+    schemas: HashMap<String, Arc<JSONSchema>>,
+    schema_sources: HashMap<String, Value>,
+    /// `precompute_canonical` 預先計算的標準形式與雜湊
+    canonical: HashMap<String, CanonicalForm>,
+    /// `x-tags` 索引: 標籤 -> schema 名稱
+    tag_index: HashMap<String, BTreeSet<String>>,
+    load_errors: HashMap<String, String>,
+    /// 編譯錯誤歷史 (時間, schema 名稱, 錯誤), 熱重載時沿用, 最多保留 `MAX_COMPILE_ERROR_HISTORY` 筆
+    compile_error_history: VecDeque<(SystemTime, String, String)>,
+    /// registry 建立的時間
+    loaded_at: SystemTime,
+    /// 最近一次被熱重載替換進來的時間
+    last_reload: Option<SystemTime>,
+    /// `auto_draft` 模式下偵測到的 draft
+    drafts: HashMap<String, Draft>,
+    /// `with_base_uri` 模式下依 `$id` 註冊的 schema 文件, 每次編譯 (含變體) 都會套用
+    documents: HashMap<String, Value>,
+    /// 載入時的 `with_base_uri`, `register_schema` 也以此指派 `$id`
+    base_uri: Option<String>,
+    /// 載入時的 `max_ref_depth`, `register_schema` 同樣檢查
+    max_ref_depth: Option<usize>,
+    /// 衍生 schema -> 基礎 schema
+    derived_from: HashMap<String, String>,
+    /// schema 名稱 -> 跨欄位規則 (依註冊順序執行)
+    cross_field_rules: HashMap<String, Vec<CrossFieldRule>>,
+    /// schema 名稱 -> 依數據欄位值選擇的子 schema
+    switches: HashMap<String, SchemaSwitch>,
+    /// `enum`/`const` 失敗降級為警告的 schemas (見 `with_lenient_enums`)
+    lenient_enums: BTreeSet<String>,
+    /// 所屬的版本 (由 `VersionedRegistry` 載入時設定)
+    version: Option<String>,
+    /// 找不到 schema 時接受數據 (見 `RegistryBuilder::permissive_unknown_schema`)
+    permissive_unknown_schema: bool,
+    /// 由源轉換而來的編譯變體快取 (鍵為 `<schema>#<變體描述>`)
+    variants: Mutex<VariantCache>,
+    /// 啟用覆蓋率追蹤後, 累積每個 schema 在驗證中看到的欄位
+    coverage: Option<Mutex<HashMap<String, CoverageAccumulator>>>,
+    /// 啟用錯誤樣本後, 每個 schema 最近的失敗記錄
+    error_samples: Option<ErrorSamples>,
+    /// 已超時但仍在背景執行的 `validate_with_deadline` 工作線程數
+    abandoned_workers: Arc<AtomicUsize>,
+}
+
+/// `debug_dump` 保留的編譯錯誤歷史筆數上限
+const MAX_COMPILE_ERROR_HISTORY: usize = 100;
+
+/// 超時後仍在背景執行的工作線程上限, 達到上限時 `validate_with_deadline` 不再開新線程
+const MAX_ABANDONED_WORKERS: usize = 16;
+
+/// 編譯變體快取的上限; 變體鍵可能含執行期資料 (如 enum 參考清單), 超過時淘汰最久未使用者
+const MAX_CACHED_VARIANTS: usize = 64;
+
+/// 依最近使用順序淘汰的編譯變體快取
+#[derive(Default)]
+struct VariantCache {
+    entries: HashMap<String, Arc<JSONSchema>>,
+    /// 最久未使用的鍵在前
+    order: VecDeque<String>,
+}
+
+impl VariantCache {
+    fn get(&mut self, key: &str) -> Option<Arc<JSONSchema>> {
+        let compiled = Arc::clone(self.entries.get(key)?);
+        self.touch(key);
+        Some(compiled)
+    }
+
+    fn insert(&mut self, key: String, compiled: Arc<JSONSchema>) {
+        if self.entries.insert(key.clone(), compiled).is_some() {
+            self.touch(&key);
+            return;
+        }
+        self.order.push_back(key);
+        while self.order.len() > MAX_CACHED_VARIANTS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    fn retain(&mut self, keep: impl Fn(&str) -> bool) {
+        self.entries.retain(|key, _| keep(key));
+        self.order.retain(|key| keep(key));
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(index) = self.order.iter().position(|cached| cached == key) {
+            if let Some(key) = self.order.remove(index) {
+                self.order.push_back(key);
+            }
+        }
+    }
+}
+
+/// 一次失敗驗證的時間與錯誤
+pub type ErrorSample = (SystemTime, Vec<ValidationErrorDetail>);
+
+/// 每個 schema 最近的失敗樣本, 以固定容量的環形緩衝限制記憶體
+struct ErrorSamples {
+    capacity: usize,
+    samples: Mutex<HashMap<String, VecDeque<ErrorSample>>>,
+}
+
+impl SchemaRegistry {
+    /// 從目錄載入所有 schemas
+    pub fn from_directory<P: AsRef<Path>>(path: P) -> Result<Self, std::io::Error> {
+        Self::builder().load(path)
+    }
+
+    /// 從任意 `SchemaSource` (資料庫、S3、git...) 載入所有 schemas
+    pub fn from_source(source: &dyn SchemaSource) -> Result<Self, SourceError> {
+        Self::builder().load_source(source)
+    }
+
+    /// 創建可配置載入選項的 builder
+    pub fn builder() -> RegistryBuilder {
+        RegistryBuilder::default()
+    }
+
+    /// 不含任何 schema 的 registry
+    fn empty() -> Self {
+        Self {
+            schemas: HashMap::new(),
+            schema_sources: HashMap::new(),
+            canonical: HashMap::new(),
+            tag_index: HashMap::new(),
+            load_errors: HashMap::new(),
+            compile_error_history: VecDeque::new(),
+            loaded_at: SystemTime::now(),
+            last_reload: None,
+            drafts: HashMap::new(),
+            documents: HashMap::new(),
+            base_uri: None,
+            max_ref_depth: None,
+            derived_from: HashMap::new(),
+            cross_field_rules: HashMap::new(),
+            switches: HashMap::new(),
+            lenient_enums: BTreeSet::new(),
+            version: None,
+            permissive_unknown_schema: false,
+            variants: Mutex::new(VariantCache::default()),
+            coverage: None,
+            error_samples: None,
+            abandoned_workers: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// 記錄載入失敗的 schema, 同時寫入編譯錯誤歷史
+    fn record_load_error(&mut self, name: String, error: String) {
+        if self.compile_error_history.len() == MAX_COMPILE_ERROR_HISTORY {
+            self.compile_error_history.pop_front();
+        }
+        self.compile_error_history.push_back((SystemTime::now(), name.clone(), error.clone()));
+        self.load_errors.insert(name, error);
+    }
+
+    /// 建立編譯選項: 套用 draft 與 `with_base_uri` 註冊的 `$id` 文件 (schema 自身除外)
+    fn compile_options(&self, draft: Option<Draft>, schema: &Value) -> CompilationOptions {
+        let mut options = JSONSchema::options();
+        if let Some(draft) = draft {
+            options.with_draft(draft);
+        }
+        let own_id = schema.get("$id").and_then(Value::as_str);
+        for (id, document) in &self.documents {
+            if Some(id.as_str()) != own_id {
+                options.with_document(id.clone(), document.clone());
+            }
+        }
+        options
+    }
+
+    /// 以 schema 的 draft 與已註冊的 `$id` 文件編譯其源或變體, 所有執行期編譯都經由此處
+    fn compile_with(
+        &self,
+        schema_name: &str,
+        schema: &Value,
+        configure: impl FnOnce(&mut CompilationOptions),
+    ) -> Result<JSONSchema, String> {
+        let mut options = self.compile_options(self.drafts.get(schema_name).copied(), schema);
+        configure(&mut options);
+        options.compile(schema).map_err(|e| e.to_string())
+    }
+
+    /// `with_base_uri` 模式下為沒有 `$id` 的 schema 指派 `<base_uri><名稱>`
+    fn assign_id(&self, name: &str, schema: &mut Value) {
+        if let (Some(base_uri), Some(object)) = (&self.base_uri, schema.as_object_mut()) {
+            object
+                .entry("$id")
+                .or_insert_with(|| Value::String(format!("{}{}", base_uri, name)));
+        }
+    }
+
+    /// `with_base_uri` 模式下以 `$id` 註冊 schema 文件, 供其他 schema 以 URI 引用
+    fn register_document(&mut self, schema: &Value) {
+        if self.base_uri.is_none() {
+            return;
+        }
+        if let Some(id) = schema.get("$id").and_then(Value::as_str) {
+            self.documents.insert(id.to_string(), schema.clone());
+        }
+    }
+
+    /// 編譯來源提供的 schemas, 編譯失敗的記錄於 `load_errors`
+    fn compile_sources(&mut self, mut sources: Vec<(String, Value)>, options: &RegistryBuilder) {
+        self.base_uri = options.base_uri.clone();
+        self.max_ref_depth = options.max_ref_depth;
+        for (name, schema) in &mut sources {
+            for transform in &options.transforms {
+                transform(name, schema);
+            }
+            self.assign_id(name, schema);
+            self.register_document(schema);
+        }
+
+        for (name, schema) in sources {
+            if let Some(max_depth) = self.max_ref_depth {
+                if let Err(e) = check_ref_chains(&schema, max_depth) {
+                    eprintln!("❌ Failed to compile schema {}: {}", name, e);
+                    self.record_load_error(name, e);
+                    continue;
+                }
+            }
+
+            if options.auto_draft {
+                match detect_draft(&schema) {
+                    Some(draft) => {
+                        self.drafts.insert(name.clone(), draft);
+                    }
+                    None => {
+                        eprintln!("❌ No supported draft compiles schema {}", name);
+                        self.record_load_error(name, "No supported draft compiles this schema".to_string());
+                        continue;
+                    }
+                }
+            }
+            let compiled = self.compile_with(&name, &schema, |_| {});
+
+            match compiled {
+                Ok(compiled) => {
+                    println!("✅ Loaded schema: {}", name);
+                    self.schema_sources.insert(name.clone(), schema);
+                    self.reindex_tags(&name);
+                    self.schemas.insert(name, Arc::new(compiled));
+                }
+                Err(e) => {
+                    eprintln!("❌ Failed to compile schema {}: {}", name, e);
+                    self.record_load_error(name, e);
+                }
+            }
+        }
+
+        println!("📊 Total schemas loaded: {}", self.schemas.len());
+    }
+
+    /// 註冊 (或替換) 一個 schema
+    ///
+    /// 以 `with_base_uri` 載入的 registry 會為新 schema 指派 `$id` 並註冊為可引用的文件。
+    /// 新源不沿用舊源偵測到的 draft; 載入時設定的 `max_ref_depth` 同樣適用。
+    pub fn register_schema(&mut self, name: &str, mut source: Value) -> Result<(), String> {
+        if let Some(max_depth) = self.max_ref_depth {
+            check_ref_chains(&source, max_depth)
+                .map_err(|e| format!("Failed to compile schema {}: {}", name, e))?;
+        }
+        self.assign_id(name, &mut source);
+        let compiled = self
+            .compile_options(None, &source)
+            .compile(&source)
+            .map_err(|e| format!("Failed to compile schema {}: {}", name, e))?;
+
+        self.drafts.remove(name);
+        self.register_document(&source);
+        self.schema_sources.insert(name.to_string(), source);
+        self.reindex_tags(name);
+        self.schemas.insert(name.to_string(), Arc::new(compiled));
+        self.load_errors.remove(name);
+        self.derived_from.remove(name);
+        self.canonical.remove(name);
+        self.invalidate_variants(name);
+        Ok(())
+    }
+
+    /// 移除 schema 及其快取, 返回被移除的源
+    pub fn unregister(&mut self, name: &str) -> Option<Value> {
+        if let Some(id) = self.schema_sources.get(name).and_then(|source| source.get("$id")).and_then(Value::as_str) {
+            self.documents.remove(id);
+        }
+        self.schemas.remove(name);
+        self.drafts.remove(name);
+        self.derived_from.remove(name);
+        self.canonical.remove(name);
+        self.invalidate_variants(name);
+        let source = self.schema_sources.remove(name);
+        self.reindex_tags(name);
+        source
+    }
+
+    /// 依目前的源更新 schema 在標籤索引中的項目
+    fn reindex_tags(&mut self, name: &str) {
+        self.tag_index.retain(|_, names| {
+            names.remove(name);
+            !names.is_empty()
+        });
+        let tags = self.schema_sources.get(name).map(schema_tags).unwrap_or_default();
+        for tag in tags {
+            self.tag_index.entry(tag).or_default().insert(name.to_string());
+        }
+    }
+
+    /// 預先計算所有 schema 源的標準形式與雜湊
+    ///
+    /// 之後的 `schema_hash`、`sources_equal` 與 `canonical_source` 直接沿用結果,
+    /// 避免在大型 schema 上重複排序鍵; `register_schema`/`unregister` 會使對應項目失效。
+    pub fn precompute_canonical(&mut self) {
+        self.canonical = self
+            .schema_sources
+            .iter()
+            .map(|(name, source)| {
+                let source = canonicalize(source);
+                let hash = content_hash(&source);
+                (name.clone(), CanonicalForm { source, hash })
+            })
+            .collect();
+    }
+
+    /// schema 源的標準形式 (鍵已排序), 有預先計算時不重新排序
+    pub fn canonical_source(&self, name: &str) -> Option<Cow<'_, Value>> {
+        if let Some(form) = self.canonical.get(name) {
+            return Some(Cow::Borrowed(&form.source));
+        }
+        self.schema_sources.get(name).map(|source| Cow::Owned(canonicalize(source)))
+    }
+
+    /// 兩個 schema 的源在忽略鍵順序後是否相同; 任一不存在時為 false
+    pub fn sources_equal(&self, a: &str, b: &str) -> bool {
+        if let (Some(a), Some(b)) = (self.canonical.get(a), self.canonical.get(b)) {
+            return a.hash == b.hash && a.source == b.source;
+        }
+        match (self.canonical_source(a), self.canonical_source(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// 取得 (或編譯並快取) 由 schema 源轉換而來的變體, 並套用 `data` 選出的 switch 分支
+    ///
+    /// 分支先以 `allOf` 附加到 schema 源, 再交給 `make_variant` 轉換, 因此變體同樣受 switch 約束;
+    /// 未知值需報錯時返回 `Err`。
+    fn compiled_variant(
+        &self,
+        schema_name: &str,
+        variant_key: &str,
+        data: &Value,
+        make_variant: impl FnOnce(&Value) -> Value,
+    ) -> Result<Arc<JSONSchema>, Vec<ValidationErrorDetail>> {
+        match self.switch_case(schema_name, data)? {
+            Some((case_key, case)) => self.cached_variant(schema_name, &format!("{};{}", variant_key, case_key), |source| {
+                make_variant(&with_all_of(source, case))
+            }),
+            None => self.cached_variant(schema_name, variant_key, make_variant),
+        }
+        .map_err(|e| vec![ValidationErrorDetail::schema_error(e)])
+    }
+
+    /// 取得 (或編譯並快取) 由 schema 源轉換而來的變體 (不套用 switch)
+    fn cached_variant(
+        &self,
+        schema_name: &str,
+        variant_key: &str,
+        make_variant: impl FnOnce(&Value) -> Value,
+    ) -> Result<Arc<JSONSchema>, String> {
+        let cache_key = format!("{}#{}", schema_name, variant_key);
+        if let Some(compiled) = self.variants.lock().unwrap().get(&cache_key) {
+            return Ok(compiled);
+        }
+
+        let source = self.schema_sources.get(schema_name)
+            .ok_or_else(|| format!("Schema '{}' not found", schema_name))?;
+        let variant = make_variant(source);
+        let compiled = self
+            .compile_with(schema_name, &variant, |_| {})
+            .map(Arc::new)
+            .map_err(|e| format!("Failed to compile variant of schema {}: {}", schema_name, e))?;
+
+        self.variants.lock().unwrap().insert(cache_key, Arc::clone(&compiled));
+        Ok(compiled)
+    }
+
+    /// 移除 schema 的所有快取變體
+    fn invalidate_variants(&mut self, name: &str) {
+        let prefix = format!("{}#", name);
+        self.variants.get_mut().unwrap().retain(|key| !key.starts_with(&prefix));
+    }
+
+    /// 複製基礎 schema 的源、套用轉換後註冊為新 schema (如「地址必填的 user」)
+    ///
+    /// 衍生關係會被記錄, 可透過 `derived_base`/`derived_schemas` 查詢。
+    /// 之後替換基礎 schema 不會自動更新衍生 schema。
+    pub fn register_derived(
+        &mut self,
+        new_name: &str,
+        base_name: &str,
+        transform: impl Fn(&mut Value),
+    ) -> Result<(), String> {
+        let mut source = self.schema_sources.get(base_name)
+            .ok_or_else(|| format!("Schema '{}' not found", base_name))?
+            .clone();
+        transform(&mut source);
+
+        self.register_schema(new_name, source)?;
+        self.derived_from.insert(new_name.to_string(), base_name.to_string());
+        Ok(())
+    }
+
+    /// 衍生 schema 的基礎 schema 名稱
+    pub fn derived_base(&self, name: &str) -> Option<&str> {
+        self.derived_from.get(name).map(String::as_str)
+    }
+
+    /// 直接衍生自 `base_name` 的 schemas
+    pub fn derived_schemas(&self, base_name: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .derived_from
+            .iter()
+            .filter(|(_, base)| *base == base_name)
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// 驗證數據是否符合指定 schema
+    ///
+    /// 啟用 `otel` feature 時, 每次驗證都包在 OpenTelemetry span 中。
+    pub fn validate(&self, schema_name: &str, data: &Value) -> ValidationResult {
+        #[cfg(feature = "otel")]
+        {
+            with_validation_span(schema_name, self.version(), || self.validate_untraced(schema_name, data))
+        }
+        #[cfg(not(feature = "otel"))]
+        {
+            self.validate_untraced(schema_name, data)
+        }
+    }
+
+    fn validate_untraced(&self, schema_name: &str, data: &Value) -> ValidationResult {
+        let (result, warnings) = self.validate_recorded(schema_name, data);
+        for warning in &warnings {
+            eprintln!("⚠️  Accepted unknown enum value in schema {}: {}", schema_name, warning);
+        }
+        result.map_err(|errors| error_messages(&errors))
+    }
+
+    /// `validate`、`validate_detailed` 與 `validate_with_warnings` 共用: 記錄覆蓋率,
+    /// lenient 降級後仍有錯誤時才記錄錯誤樣本
+    fn validate_recorded(&self, schema_name: &str, data: &Value) -> (DetailedResult, Vec<ValidationErrorDetail>) {
+        self.record_coverage(schema_name, data);
+        let (result, warnings) = self.probe(schema_name, data);
+        if let Err(details) = &result {
+            if self.error_samples.is_some() && self.schemas.contains_key(schema_name) {
+                self.record_error_sample(schema_name, details.clone());
+            }
+        }
+        (result, warnings)
+    }
+
+    /// 套用 switch 後完整驗證, 不記錄覆蓋率與錯誤樣本
+    fn probe(&self, schema_name: &str, data: &Value) -> (DetailedResult, Vec<ValidationErrorDetail>) {
+        let Some(schema) = self.schemas.get(schema_name) else {
+            let result = self.unknown_schema(schema_name)
+                .map_err(|_| vec![ValidationErrorDetail::schema_not_found(schema_name)]);
+            return (result, Vec::new());
+        };
+        match self.switched_schema(schema_name, schema, data) {
+            Ok(schema) => self.evaluate(&schema, schema_name, data),
+            Err(errors) => (Err(errors), Vec::new()),
+        }
+    }
+
+    /// 以編譯好的 schema (或其變體) 驗證, 再執行 `settle`
+    fn evaluate(
+        &self,
+        schema: &JSONSchema,
+        schema_name: &str,
+        data: &Value,
+    ) -> (DetailedResult, Vec<ValidationErrorDetail>) {
+        self.settle(schema_name, data, structural_details(schema, data))
+    }
+
+    /// 結構驗證之後所有入口共用的步驟: lenient schema 的 `enum`/`const` 失敗降級為警告,
+    /// 沒有其他錯誤時才執行跨欄位規則。返回 (結果, 警告)。
+    fn settle(
+        &self,
+        schema_name: &str,
+        data: &Value,
+        structural: Vec<ValidationErrorDetail>,
+    ) -> (DetailedResult, Vec<ValidationErrorDetail>) {
+        let (warnings, errors): (Vec<_>, Vec<_>) = structural
+            .into_iter()
+            .partition(|detail| self.is_demoted(schema_name, detail));
+        let result = if errors.is_empty() {
+            self.cross_field_details(schema_name, data)
+        } else {
+            Err(errors)
+        };
+        (result, warnings)
+    }
+
+    /// 所屬的版本 (由 `VersionedRegistry` 載入時設定)
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// 為 schema 註冊跨欄位規則, 只在結構驗證通過後執行, 失敗訊息併入錯誤列表
+    ///
+    /// 用於 JSON Schema 難以表達的業務規則, 取代散落在驗證調用之後的檢查。
+    pub fn with_cross_field_rule(
+        mut self,
+        schema_name: &str,
+        f: impl Fn(&Value) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        self.cross_field_rules
+            .entry(schema_name.to_string())
+            .or_default()
+            .push(Arc::new(f));
+        self
+    }
+
+    /// 將 schema 的 `enum`/`const` 失敗降級為警告, 其他失敗仍為錯誤
+    ///
+    /// 用於伺服器端先加入新 enum 值、客戶端尚未更新的發布期間。此選項會放寬驗證,
+    /// 因此必須逐一 schema 明確啟用; 警告由 `validate_with_warnings` 返回,
+    /// `validate` 只會記錄到日誌。
+    pub fn with_lenient_enums(mut self, schema_name: &str) -> Self {
+        self.lenient_enums.insert(schema_name.to_string());
+        self
+    }
+
+    /// 驗證數據, 另外返回被降級為警告的 `enum`/`const` 失敗
+    ///
+    /// 未以 `with_lenient_enums` 啟用的 schema 不會產生警告, 結果與 `validate_detailed` 相同。
+    pub fn validate_with_warnings(
+        &self,
+        schema_name: &str,
+        data: &Value,
+    ) -> (ValidationResult, Vec<ValidationErrorDetail>) {
+        let (result, warnings) = self.validate_recorded(schema_name, data);
+        (result.map_err(|errors| error_messages(&errors)), warnings)
+    }
+
+    /// 錯誤是否為 `with_lenient_enums` 降級為警告的 `enum`/`const` 失敗
+    fn is_demoted(&self, schema_name: &str, detail: &ValidationErrorDetail) -> bool {
+        self.lenient_enums.contains(schema_name) && is_enum_failure(detail)
+    }
+
+    /// 驗證時讀取 `data[field]`, 將 `mapping` 中對應的子 schema 以 `allOf` 附加到 schema
+    ///
+    /// 用於多型 payload (如依 `kind` 選擇 `card`/`bank` 的欄位規則), 比 `if/then` 更直接。
+    /// 非字串的欄位值以其 JSON 文字比對 (如 `1`, `true`)。
+    pub fn with_switch(
+        mut self,
+        schema_name: &str,
+        field: &str,
+        mapping: HashMap<String, Value>,
+        on_unknown: UnknownSwitchValue,
+    ) -> Self {
+        self.invalidate_variants(schema_name);
+        self.switches.insert(schema_name.to_string(), SchemaSwitch {
+            field: field.to_string(),
+            cases: mapping,
+            on_unknown,
+        });
+        self
+    }
+
+    /// 套用 switch 後用於驗證數據的 schema
+    ///
+    /// 未設定 switch 或未知值落空時返回 `base`; 未知值需報錯時返回 `Err`。
+    fn switched_schema(
+        &self,
+        schema_name: &str,
+        base: &Arc<JSONSchema>,
+        data: &Value,
+    ) -> Result<Arc<JSONSchema>, Vec<ValidationErrorDetail>> {
+        match self.switch_case(schema_name, data)? {
+            Some((key, case)) => self
+                .cached_variant(schema_name, &key, |source| with_all_of(source, case))
+                .map_err(|e| vec![ValidationErrorDetail::schema_error(e)]),
+            None => Ok(Arc::clone(base)),
+        }
+    }
+
+    /// 依數據選出的 switch 分支 (變體鍵, 子 schema)
+    ///
+    /// 未設定 switch 或未知值落空時返回 None; 未知值需報錯時返回 `Err`。
+    fn switch_case(
+        &self,
+        schema_name: &str,
+        data: &Value,
+    ) -> Result<Option<(String, &Value)>, Vec<ValidationErrorDetail>> {
+        let Some(switch) = self.switches.get(schema_name) else {
+            return Ok(None);
+        };
+        let value = data.get(&switch.field).map(|value| match value {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        });
+        let case = value.as_ref().and_then(|value| switch.cases.get(value).map(|case| (value, case)));
+
+        match (case, switch.on_unknown) {
+            (Some((value, case)), _) => Ok(Some((format!("switch={}", value), case))),
+            (None, UnknownSwitchValue::FallThrough) => Ok(None),
+            (None, UnknownSwitchValue::Error) => Err(vec![ValidationErrorDetail {
+                instance_path: format!("/{}", escape_pointer_segment(&switch.field)),
+                schema_path: String::new(),
+                keyword: "x-switch".to_string(),
+                message: match value {
+                    Some(value) => format!("Unknown value '{}' for switch field '{}'", value, switch.field),
+                    None => format!("Missing switch field '{}'", switch.field),
+                },
+                schema_hash: None,
+                suggestion: None,
+            }]),
+        }
+    }
+
+    /// 執行 schema 的所有跨欄位規則, 收集全部失敗
+    fn check_cross_field_rules(&self, schema_name: &str, data: &Value) -> ValidationResult {
+        let failures: Vec<String> = self
+            .cross_field_rules
+            .get(schema_name)
+            .into_iter()
+            .flatten()
+            .filter_map(|rule| rule(data).err())
+            .collect();
+
+        if failures.is_empty() { Ok(()) } else { Err(failures) }
+    }
+
+    /// 跨欄位規則的失敗, 以結構化錯誤表示
+    fn cross_field_details(&self, schema_name: &str, data: &Value) -> Result<(), Vec<ValidationErrorDetail>> {
+        self.check_cross_field_rules(schema_name, data)
+            .map_err(|failures| failures.into_iter().map(ValidationErrorDetail::cross_field).collect())
+    }
+
+    /// 啟用覆蓋率追蹤: 之後每次 `validate` 都會記錄看到的屬性與 enum 值
+    ///
+    /// 追蹤有鎖競爭成本, 預設關閉。
+    pub fn enable_coverage_tracking(&mut self) {
+        self.coverage.get_or_insert_with(|| Mutex::new(HashMap::new()));
+    }
+
+    /// 至今所有 `validate` 累積的覆蓋率, 用於找出從未使用的 schema 欄位
+    ///
+    /// 未啟用追蹤或 schema 不存在時返回 None。
+    pub fn accumulated_coverage(&self, name: &str) -> Option<CoverageReport> {
+        let coverage = self.coverage.as_ref()?.lock().unwrap();
+        let source = self.schema_sources.get(name)?;
+
+        let mut declared_properties = BTreeSet::new();
+        let mut declared_enums = BTreeMap::new();
+        collect_declared_fields(source, "", &mut declared_properties, &mut declared_enums);
+
+        let empty = CoverageAccumulator::default();
+        let seen = coverage.get(name).unwrap_or(&empty);
+
+        let unseen_enum_values = declared_enums
+            .into_iter()
+            .filter_map(|(path, values)| {
+                let seen_values = seen.enum_values.get(&path);
+                let unseen: BTreeSet<String> = values
+                    .into_iter()
+                    .filter(|v| !seen_values.is_some_and(|s| s.contains(v)))
+                    .collect();
+                (!unseen.is_empty()).then_some((path, unseen))
+            })
+            .collect();
+
+        Some(CoverageReport {
+            unseen_properties: declared_properties.difference(&seen.properties).cloned().collect(),
+            seen_properties: seen.properties.clone(),
+            seen_enum_values: seen.enum_values.clone(),
+            unseen_enum_values,
+        })
+    }
+
+    /// 開始保存每個 schema 最近 `capacity` 次失敗的錯誤 (用於營運儀表板)
+    pub fn enable_error_samples(&mut self, capacity: usize) {
+        self.error_samples = Some(ErrorSamples {
+            capacity: capacity.max(1),
+            samples: Mutex::new(HashMap::new()),
+        });
+    }
+
+    /// schema 最近的 `n` 筆失敗樣本 (新到舊); 未啟用錯誤樣本時為空
+    pub fn recent_errors(&self, name: &str, n: usize) -> Vec<ErrorSample> {
+        let Some(error_samples) = &self.error_samples else {
+            return Vec::new();
+        };
+        let samples = error_samples.samples.lock().unwrap();
+        samples
+            .get(name)
+            .map(|ring| ring.iter().rev().take(n).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 保存失敗樣本, 超出容量時丟棄最舊的
+    fn record_error_sample(&self, schema_name: &str, errors: Vec<ValidationErrorDetail>) {
+        let Some(error_samples) = &self.error_samples else {
+            return;
+        };
+        let mut samples = error_samples.samples.lock().unwrap();
+        let ring = samples.entry(schema_name.to_string()).or_default();
+        if ring.len() == error_samples.capacity {
+            ring.pop_front();
+        }
+        ring.push_back((SystemTime::now(), errors));
+    }
+
+    fn record_coverage(&self, schema_name: &str, data: &Value) {
+        let (Some(coverage), Some(source)) = (&self.coverage, self.schema_sources.get(schema_name)) else {
+            return;
+        };
+        let mut coverage = coverage.lock().unwrap();
+        let accumulator = coverage.entry(schema_name.to_string()).or_default();
+        record_seen_fields(source, data, "", accumulator);
+    }
+
+    /// 驗證數據並返回成本統計
+    ///
+    /// jsonschema 不提供實際走訪的節點數, `nodes_visited` 以數據文件的節點總數近似。
+    pub fn validate_with_stats(&self, schema_name: &str, data: &Value) -> (ValidationResult, ValidationStats) {
+        let started = Instant::now();
+        let result = self.validate(schema_name, data);
+        let stats = ValidationStats {
+            nodes_visited: count_nodes(data),
+            errors_found: result.as_ref().err().map_or(0, Vec::len),
+            duration: started.elapsed(),
+        };
+        (result, stats)
+    }
+
+    /// 在時限內驗證數據, 超時返回 `DeadlineError::TimedOut`
+    ///
+    /// 結構驗證在工作線程中執行; jsonschema 無法中斷, 超時後該線程仍會在背景跑完,
+    /// 但調用者不再等待。背景中的超時線程達 `MAX_ABANDONED_WORKERS` 個時, 新的調用
+    /// 不開線程也不驗證, 直接返回 `DeadlineError::Overloaded` (與真正的超時區分),
+    /// 待背景線程跑完後恢復。跨欄位規則在結構驗證通過後於調用者線程執行, 不計入時限。
+    pub fn validate_with_deadline(
+        &self,
+        schema_name: &str,
+        data: &Value,
+        timeout: Duration,
+    ) -> Result<ValidationResult, DeadlineError> {
+        let Some(schema) = self.schemas.get(schema_name) else {
+            return Ok(self.unknown_schema(schema_name));
+        };
+        self.record_coverage(schema_name, data);
+        let schema = match self.switched_schema(schema_name, schema, data) {
+            Ok(schema) => schema,
+            Err(errors) => return Ok(Err(error_messages(&errors))),
+        };
+
+        if self.abandoned_workers.load(Ordering::SeqCst) >= MAX_ABANDONED_WORKERS {
+            return Err(DeadlineError::Overloaded(Overloaded { limit: MAX_ABANDONED_WORKERS }));
+        }
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let owned = data.clone();
+        // 工作線程與調用者誰後設定 `settled`, 就由誰處理背景線程的計數
+        let settled = Arc::new(AtomicBool::new(false));
+        let worker_settled = Arc::clone(&settled);
+        let abandoned = Arc::clone(&self.abandoned_workers);
+        std::thread::spawn(move || {
+            let _ = tx.send(structural_details(&schema, &owned));
+            if worker_settled.swap(true, Ordering::SeqCst) {
+                abandoned.fetch_sub(1, Ordering::SeqCst);
+            }
+        });
+
+        let details = match rx.recv_timeout(timeout) {
+            Ok(details) => details,
+            Err(_) if !settled.swap(true, Ordering::SeqCst) => {
+                self.abandoned_workers.fetch_add(1, Ordering::SeqCst);
+                return Err(DeadlineError::TimedOut(TimedOut { limit: timeout }));
+            }
+            // 工作線程恰好在超時後完成, 結果已在 channel 中
+            Err(_) => rx.recv().map_err(|_| DeadlineError::TimedOut(TimedOut { limit: timeout }))?,
+        };
+        let (result, _) = self.settle(schema_name, data, details);
+        Ok(result.map_err(|errors| error_messages(&errors)))
+    }
+
+    /// 在背景線程中驗證, 逐一推送事件 (`Started`, 每個 `Error`, `Finished`)
+    ///
+    /// 錯誤一產生就送出, 適合透過 websocket 推給「邊編輯邊驗證」的 UI 逐步渲染。
+    /// 結構驗證通過後才執行跨欄位規則, 其失敗同樣以 `Error` 推送。
+    /// receiver 被 drop 後驗證提前停止。
+    pub fn validate_observable(&self, schema_name: &str, data: &Value) -> Receiver<ValidationEvent> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let _ = tx.send(ValidationEvent::Started { schema_name: schema_name.to_string() });
+
+        let Some(schema) = self.schemas.get(schema_name) else {
+            let error_count = match self.unknown_schema(schema_name) {
+                Ok(()) => 0,
+                Err(_) => {
+                    let _ = tx.send(ValidationEvent::Error(ValidationErrorDetail::schema_not_found(schema_name)));
+                    1
+                }
+            };
+            let _ = tx.send(ValidationEvent::Finished { error_count });
+            return rx;
+        };
+        self.record_coverage(schema_name, data);
+        let schema = match self.switched_schema(schema_name, schema, data) {
+            Ok(schema) => schema,
+            Err(errors) => {
+                let error_count = errors.len();
+                for error in errors {
+                    let _ = tx.send(ValidationEvent::Error(error));
+                }
+                let _ = tx.send(ValidationEvent::Finished { error_count });
+                return rx;
+            }
+        };
+        let rules = self.cross_field_rules.get(schema_name).cloned().unwrap_or_default();
+        let lenient = self.lenient_enums.contains(schema_name);
+        let data = data.clone();
+        std::thread::spawn(move || {
+            let mut error_count = 0;
+            for error in schema.iter_errors(&data) {
+                let detail = ValidationErrorDetail::from_error(&error);
+                if lenient && is_enum_failure(&detail) {
+                    continue;
+                }
+                error_count += 1;
+                if tx.send(ValidationEvent::Error(detail)).is_err() {
+                    return;
+                }
+            }
+            if error_count == 0 {
+                for message in rules.iter().filter_map(|rule| rule(&data).err()) {
+                    error_count += 1;
+                    if tx.send(ValidationEvent::Error(ValidationErrorDetail::cross_field(message))).is_err() {
+                        return;
+                    }
+                }
+            }
+            let _ = tx.send(ValidationEvent::Finished { error_count });
+        });
+        rx
+    }
+
+    /// 遞迴掃描目錄下所有 `.json` 數據文件, 以 `name_fn` 推導 schema 名稱後驗證
+    ///
+    /// 無法讀取 (如非 UTF-8) 或解析的文件記為該文件的驗證失敗; 只有目錄走訪失敗才返回 `Err`。
+    /// `name_fn` 返回 None 的文件列在 `unmatched`。
+    pub fn validate_data_directory<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        name_fn: impl Fn(&Path) -> Option<String>,
+    ) -> Result<DataDirectoryReport, std::io::Error> {
+        let mut report = DataDirectoryReport::default();
+        let mut pending = vec![dir.as_ref().to_path_buf()];
+
+        while let Some(current) = pending.pop() {
+            for entry in fs::read_dir(&current)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                if path.extension().and_then(|s| s.to_str()) != Some("json") {
+                    continue;
+                }
+
+                let Some(schema_name) = name_fn(&path) else {
+                    report.unmatched.push(path);
+                    continue;
+                };
+                let result = match fs::read_to_string(&path) {
+                    Ok(text) => match serde_json::from_str::<Value>(&text) {
+                        Ok(data) => self.validate(&schema_name, &data),
+                        Err(e) => Err(vec![format!("Invalid JSON in {}: {}", path.display(), e)]),
+                    },
+                    Err(e) => Err(vec![format!("Failed to read {}: {}", path.display(), e)]),
+                };
+                report.results.insert(path, result);
+            }
+        }
+
+        report.unmatched.sort();
+        Ok(report)
+    }
+
+    /// 批量驗證, 每個項目各自有時限
+    ///
+    /// 超時的項目單獨標記為 `DeadlineError::TimedOut`, 其餘項目繼續驗證,
+    /// 避免單一異常輸入拖住整個批次。背景中的超時線程達上限後, 其餘項目
+    /// 不再開線程, 標記為 `DeadlineError::Overloaded` (見 `validate_with_deadline`)。
+    pub fn validate_batch_with_deadline(
+        &self,
+        schema_name: &str,
+        items: &[Value],
+        per_item: Duration,
+    ) -> Vec<Result<ValidationResult, DeadlineError>> {
+        items
+            .iter()
+            .map(|item| self.validate_with_deadline(schema_name, item, per_item))
+            .collect()
+    }
+
+    /// 以 rayon 平行驗證數據的各個頂層屬性 (需要 `rayon` feature)
+    ///
+    /// 這是逐欄位的平行檢查: 每個屬性只以其子 schema 驗證 (保留 `$defs`/`definitions`
+    /// 供 `$ref` 解析), 不檢查頂層的 `required`、`additionalProperties` 與跨欄位規則,
+    /// 需要完整結果時仍應使用 `validate`。`with_switch` 選出的分支中該屬性的約束同樣套用。
+    /// schema 未宣告的屬性不列入結果; schema 不存在或 switch 值未知時返回以空字串為鍵的錯誤。
+    #[cfg(feature = "rayon")]
+    pub fn validate_fields_par(&self, schema_name: &str, data: &Value) -> HashMap<String, ValidationResult> {
+        use rayon::prelude::*;
+
+        let Some(source) = self.schema_sources.get(schema_name) else {
+            return match self.unknown_schema(schema_name) {
+                Ok(()) => HashMap::new(),
+                Err(errors) => HashMap::from([(String::new(), Err(errors))]),
+            };
+        };
+        let case = match self.switch_case(schema_name, data) {
+            Ok(case) => case,
+            Err(errors) => return HashMap::from([(String::new(), Err(error_messages(&errors)))]),
+        };
+        let declared = property_names(source);
+        let fields: Vec<(&String, &Value)> = data
+            .as_object()
+            .map(|object| object.iter().filter(|(name, _)| declared.contains(name)).collect())
+            .unwrap_or_default();
+
+        fields
+            .into_par_iter()
+            .map(|(name, value)| {
+                // switch 分支對此屬性的約束 (分支的頂層 `required` 與其他屬性不適用於逐欄位檢查)
+                let case_property = case.as_ref().and_then(|(case_key, case)| {
+                    let property = case.pointer(&format!("/properties/{}", escape_pointer_segment(name)))?;
+                    Some((case_key, property))
+                });
+                let key = match &case_property {
+                    Some((case_key, _)) => format!("field={};{}", name, case_key),
+                    None => format!("field={}", name),
+                };
+                let result = self
+                    .cached_variant(schema_name, &key, |source| {
+                        let mut variant = serde_json::Map::new();
+                        for keyword in ["$schema", "$id", "$defs", "definitions"] {
+                            if let Some(value) = source.get(keyword) {
+                                variant.insert(keyword.to_string(), value.clone());
+                            }
+                        }
+                        variant.insert(
+                            "properties".to_string(),
+                            serde_json::json!({ name.as_str(): source["properties"][name.as_str()] }),
+                        );
+                        if let Some((_, property)) = case_property {
+                            let case = serde_json::json!({"properties": { name.as_str(): property }});
+                            variant.insert("allOf".to_string(), serde_json::json!([case]));
+                        }
+                        Value::Object(variant)
+                    })
+                    .map_err(|e| vec![e])
+                    .and_then(|compiled| {
+                        let errors: Vec<ValidationErrorDetail> =
+                            structural_details(&compiled, &serde_json::json!({ name.as_str(): value }))
+                                .into_iter()
+                                .filter(|detail| !self.is_demoted(schema_name, detail))
+                                .collect();
+                        if errors.is_empty() { Ok(()) } else { Err(error_messages(&errors)) }
+                    });
+                (name.clone(), result)
+            })
+            .collect()
+    }
+
+    /// 以臨時覆蓋的 `format` 驗證函數驗證 (如單一端點要求嚴格的 `email`)
+    ///
+    /// 每次調用都會重新編譯 schema, 成本遠高於 `validate`;
+    /// 只應用於少數需要不同 format 規則的路徑。
+    pub fn validate_with_format_overrides(
+        &self,
+        schema_name: &str,
+        overrides: HashMap<String, FormatValidator>,
+        data: &Value,
+    ) -> ValidationResult {
+        let Some(source) = self.schema_sources.get(schema_name) else {
+            return self.unknown_schema(schema_name);
+        };
+        let source = match self.switch_case(schema_name, data).map_err(|errors| error_messages(&errors))? {
+            Some((_, case)) => Cow::Owned(with_all_of(source, case)),
+            None => Cow::Borrowed(source),
+        };
+
+        let compiled = self
+            .compile_with(schema_name, &source, |options| {
+                options.should_validate_formats(true);
+                for (format, check) in overrides {
+                    options.with_format(format, move |value: &str| check(value));
+                }
+            })
+            .map_err(|e| vec![format!("Failed to compile schema {}: {}", schema_name, e)])?;
+
+        self.evaluate(&compiled, schema_name, data).0.map_err(|errors| error_messages(&errors))
+    }
+
+    /// 將額外的屬性視為頂層必填後驗證 (嚴格匯入模式)
+    ///
+    /// 變體依必填集合的雜湊快取; 名稱未宣告於 `properties` 時返回配置錯誤。
+    pub fn validate_with_extra_required(
+        &self,
+        schema_name: &str,
+        extra_required: &[&str],
+        data: &Value,
+    ) -> ValidationResult {
+        let Some(source) = self.schema_sources.get(schema_name) else {
+            return self.unknown_schema(schema_name);
+        };
+
+        let declared = property_names(source);
+        let undeclared: Vec<String> = extra_required
+            .iter()
+            .filter(|name| !declared.iter().any(|declared| declared == *name))
+            .map(|name| format!("Extra required field '{}' is not declared in schema {}", name, schema_name))
+            .collect();
+        if !undeclared.is_empty() {
+            return Err(undeclared);
+        }
+
+        let required: BTreeSet<&str> = extra_required.iter().copied().collect();
+        let key = format!("required={}", content_hash(&serde_json::json!(required)));
+        let compiled = self
+            .compiled_variant(schema_name, &key, data, |source| {
+                let mut variant = source.clone();
+                let mut names: BTreeSet<String> = required_names(source).into_iter().map(String::from).collect();
+                names.extend(required.iter().map(|name| name.to_string()));
+                variant["required"] = serde_json::json!(names);
+                variant
+            })
+            .map_err(|errors| error_messages(&errors))?;
+
+        self.evaluate(&compiled, schema_name, data).0.map_err(|errors| error_messages(&errors))
+    }
+
+    /// 以執行期載入的值取代 `path` (點號屬性路徑, 如 `address.country`) 的 `enum` 後驗證
+    ///
+    /// 變體依路徑與值的雜湊快取, 參考資料更新時不需重新產生 schema 文件; 快取最多保留
+    /// `MAX_CACHED_VARIANTS` 個變體, 過期的清單會被淘汰。路徑未宣告於 schema 時返回配置錯誤。
+    pub fn validate_with_enum_override(
+        &self,
+        schema_name: &str,
+        path: &str,
+        values: &[Value],
+        data: &Value,
+    ) -> ValidationResult {
+        let Some(source) = self.schema_sources.get(schema_name) else {
+            return self.unknown_schema(schema_name);
+        };
+
+        let pointer: String = path
+            .split('.')
+            .map(|segment| format!("/properties/{}", escape_pointer_segment(segment)))
+            .collect();
+        if !source.pointer(&pointer).is_some_and(Value::is_object) {
+            return Err(vec![format!("Property path '{}' not found in schema {}", path, schema_name)]);
+        }
+
+        let key = format!("enum:{}={}", path, content_hash(&Value::from(values)));
+        let compiled = self
+            .compiled_variant(schema_name, &key, data, |source| {
+                let mut variant = source.clone();
+                if let Some(property) = variant.pointer_mut(&pointer).and_then(Value::as_object_mut) {
+                    property.insert("enum".to_string(), Value::from(values));
+                }
+                variant
+            })
+            .map_err(|errors| error_messages(&errors))?;
+
+        self.evaluate(&compiled, schema_name, data).0.map_err(|errors| error_messages(&errors))
+    }
+
+    /// 驗證數據並返回結構化錯誤
+    pub fn validate_detailed(
+        &self,
+        schema_name: &str,
+        data: &Value,
+    ) -> Result<(), Vec<ValidationErrorDetail>> {
+        self.validate_recorded(schema_name, data).0
+    }
+
+    /// 依選項驗證數據並返回結構化錯誤
+    pub fn validate_with_options(
+        &self,
+        schema_name: &str,
+        data: &Value,
+        opts: &ValidateOptions,
+    ) -> Result<(), Vec<ValidationErrorDetail>> {
+        let Some(base) = self.schemas.get(schema_name) else {
+            return self.unknown_schema(schema_name)
+                .map_err(|_| vec![ValidationErrorDetail::schema_not_found(schema_name)]);
+        };
+
+        let data: Cow<Value> = if opts.treat_null_as_missing {
+            let mut cleaned = data.clone();
+            remove_null_properties(&mut cleaned);
+            Cow::Owned(cleaned)
+        } else {
+            Cow::Borrowed(data)
+        };
+        let data = data.as_ref();
+
+        if let Some(max_bytes) = opts.max_string_bytes {
+            if let Some(path) = find_oversized_string(data, max_bytes, "") {
+                return Err(vec![ValidationErrorDetail {
+                    message: format!("String exceeds {} bytes", max_bytes),
+                    instance_path: path,
+                    schema_path: String::new(),
+                    keyword: "maxStringBytes".to_string(),
+                    schema_hash: None,
+                    suggestion: None,
+                }]);
+            }
+        }
+
+        let schema = match opts.numeric_epsilon.filter(|e| *e > 0.0) {
+            Some(epsilon) => self.compiled_variant(schema_name, &format!("epsilon={}", epsilon), data, |source| {
+                relax_numeric_bounds(source, epsilon)
+            })?,
+            None => self.switched_schema(schema_name, base, data)?,
+        };
+
+        self.record_coverage(schema_name, data);
+        let (result, warnings) = self.evaluate(&schema, schema_name, data);
+        for warning in &warnings {
+            eprintln!("⚠️  Accepted unknown enum value in schema {}: {}", schema_name, warning);
+        }
+        let Err(details) = result else {
+            return Ok(());
+        };
+
+        let hash = if opts.include_schema_hash { self.schema_hash(schema_name) } else { None };
+        let mut errors = Vec::new();
+        let mut truncated_at = None;
+        let mut payload_bytes = 0;
+        for mut detail in details {
+            detail.schema_hash = hash.clone();
+
+            if let Some(max_bytes) = opts.max_error_bytes {
+                payload_bytes += detail.to_json().to_string().len();
+                if payload_bytes > max_bytes {
+                    truncated_at = Some(max_bytes);
+                    break;
+                }
+            }
+            errors.push(detail);
+        }
+
+        if opts.sort_errors {
+            errors.sort_by(|a, b| {
+                (&a.instance_path, &a.keyword, &a.message).cmp(&(&b.instance_path, &b.keyword, &b.message))
+            });
+        }
+        if let Some(max_bytes) = truncated_at {
+            errors.push(ValidationErrorDetail::truncated(max_bytes));
+        }
+        Err(errors)
+    }
+
+    /// schema 源的內容雜湊 (與鍵順序和空白無關), 用於識別 schema 版本
+    pub fn schema_hash(&self, name: &str) -> Option<String> {
+        if let Some(form) = self.canonical.get(name) {
+            return Some(form.hash.clone());
+        }
+        let source = self.schema_sources.get(name)?;
+        Some(content_hash(&canonicalize(source)))
+    }
+
+    /// 依 keyword 統計錯誤數量 (`required` -> 2, `type` -> 1...)
+    ///
+    /// 跨語料累加可看出最常見的失敗類型; schema 不存在時計入 `schema`。
+    /// 統計不記錄覆蓋率與錯誤樣本。
+    pub fn error_histogram(&self, schema_name: &str, data: &Value) -> HashMap<String, usize> {
+        let mut histogram = HashMap::new();
+        if let Err(errors) = self.probe(schema_name, data).0 {
+            for error in errors {
+                *histogram.entry(error.keyword).or_insert(0) += 1;
+            }
+        }
+        histogram
+    }
+
+    /// 依錯誤數量排序候選 schemas (錯誤最少者最吻合), 用於歸類無法完全匹配的文件
+    ///
+    /// `candidates` 為 None 時比對所有已載入的 schemas; 未載入的候選會被略過。
+    /// 錯誤數相同時依名稱排序。比對不記錄覆蓋率與錯誤樣本。
+    pub fn best_match(&self, data: &Value, candidates: Option<&[&str]>) -> Vec<(String, usize)> {
+        let names: Vec<&str> = match candidates {
+            Some(candidates) => candidates
+                .iter()
+                .copied()
+                .filter(|name| self.schemas.contains_key(*name))
+                .collect(),
+            None => self.schemas.keys().map(String::as_str).collect(),
+        };
+
+        let mut ranked: Vec<(String, usize)> = names
+            .into_iter()
+            .map(|name| {
+                let error_count = self.probe(name, data).0.err().map_or(0, |errors| errors.len());
+                (name.to_string(), error_count)
+            })
+            .collect();
+        ranked.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        ranked
+    }
+
+    /// 驗證數據, 失敗時返回 `SchemaValidationError`
+    pub fn try_validate(&self, schema_name: &str, data: &Value) -> Result<(), SchemaValidationError> {
+        self.validate_detailed(schema_name, data)
+            .map_err(|errors| SchemaValidationError {
+                schema_name: schema_name.to_string(),
+                errors,
+            })
+    }
+
+    /// 先以 `primary` 驗證, 失敗時改用較寬鬆的 `fallback` (legacy) schema
+    ///
+    /// 用於新舊 payload 並存的相容期; 結果保留主 schema 的錯誤以便追蹤仍走舊路徑的數據。
+    pub fn validate_with_fallback(&self, primary: &str, fallback: &str, data: &Value) -> FallbackResult {
+        let primary_errors = match self.validate(primary, data) {
+            Ok(()) => return FallbackResult::Primary,
+            Err(errors) => errors,
+        };
+        match self.validate(fallback, data) {
+            Ok(()) => FallbackResult::Fallback { primary_errors },
+            Err(fallback_errors) => FallbackResult::Rejected { primary_errors, fallback_errors },
+        }
+    }
+
+    /// 快速檢查數據是否有效 (不返回詳細錯誤)
+    pub fn is_valid(&self, schema_name: &str, data: &Value) -> bool {
+        self.schemas
+            .get(schema_name)
+            .map(|schema| match self.switched_schema(schema_name, schema, data) {
+                Ok(schema) if schema.is_valid(data) => self.check_cross_field_rules(schema_name, data).is_ok(),
+                Ok(schema) => {
+                    self.lenient_enums.contains(schema_name) && self.evaluate(&schema, schema_name, data).0.is_ok()
+                }
+                Err(_) => false,
+            })
+            .unwrap_or(self.permissive_unknown_schema)
+    }
+
+    /// 找不到 schema 時的結果: 預設為錯誤, permissive 模式下警告後接受
+    fn unknown_schema(&self, schema_name: &str) -> ValidationResult {
+        if self.permissive_unknown_schema {
+            eprintln!("⚠️  Schema '{}' not found, accepting data (permissive mode)", schema_name);
+            Ok(())
+        } else {
+            Err(vec![format!("Schema '{}' not found", schema_name)])
+        }
+    }
+
+    /// 獲取所有已載入的 schema 名稱
+    pub fn list_schemas(&self) -> Vec<&String> {
+        self.schemas.keys().collect()
+    }
+
+    /// 用於支援工單的診斷快照: schema 名稱、雜湊、draft、載入與重載時間、編譯錯誤歷史
+    ///
+    /// 不包含 schema 內容, 需要時使用 `debug_dump_with_sources`。時間以 Unix 秒數表示。
+    /// `draft` 為實際生效的 draft (明確設定 > `$schema` 宣告 > jsonschema 預設)。
+    /// 未呼叫 `precompute_canonical` 時每次都會重新計算所有 schema 的正規化雜湊,
+    /// 成本與 schema 總大小成正比, 不適合放在高頻路徑。
+    pub fn debug_dump(&self) -> Value {
+        self.dump(false)
+    }
+
+    /// 同 `debug_dump`, 但每個 schema 附上完整的源
+    pub fn debug_dump_with_sources(&self) -> Value {
+        self.dump(true)
+    }
+
+    fn dump(&self, include_sources: bool) -> Value {
+        let unix_seconds = |time: &SystemTime| {
+            time.duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+        };
+
+        let mut names: Vec<&String> = self.schemas.keys().collect();
+        names.sort();
+        let schemas: Vec<Value> = names
+            .into_iter()
+            .map(|name| {
+                let mut entry = serde_json::json!({
+                    "name": name,
+                    "hash": self.schema_hash(name),
+                    "draft": format!("{:?}", self.effective_draft(name)),
+                    "derived_from": self.derived_from.get(name),
+                    "tags": self.tags(name),
+                });
+                if include_sources {
+                    entry["source"] = self.schema_sources.get(name).cloned().unwrap_or(Value::Null);
+                }
+                entry
+            })
+            .collect();
+
+        let load_errors: BTreeMap<&String, &String> = self.load_errors.iter().collect();
+        let history: Vec<Value> = self
+            .compile_error_history
+            .iter()
+            .map(|(time, name, error)| serde_json::json!({"at": unix_seconds(time), "schema": name, "error": error}))
+            .collect();
+
+        serde_json::json!({
+            "version": self.version,
+            "loaded_at": unix_seconds(&self.loaded_at),
+            "last_reload": self.last_reload.as_ref().map(unix_seconds),
+            "schema_count": self.schemas.len(),
+            "schemas": schemas,
+            "load_errors": load_errors,
+            "compile_error_history": history,
+            "cached_variants": self.variants.lock().unwrap().len(),
+            "permissive_unknown_schema": self.permissive_unknown_schema,
+        })
+    }
+
+    /// 獲取 schema 的源 JSON (用於文檔生成)
+    pub fn get_schema_source(&self, name: &str) -> Option<&Value> {
+        self.schema_sources.get(name)
+    }
+
+    /// 帶有指定 `x-tags` 標籤的 schemas (依名稱排序)
+    pub fn schemas_with_tag(&self, tag: &str) -> Vec<String> {
+        self.tag_index
+            .get(tag)
+            .map(|names| names.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// schema 源中 `x-tags` 陣列的標籤
+    pub fn tags(&self, name: &str) -> Vec<String> {
+        self.schema_sources.get(name).map(schema_tags).unwrap_or_default()
+    }
+
+    /// 找出所有宣告了指定屬性名的 schemas (遞迴搜尋巢狀 schema)
+    ///
+    /// 用於治理 ("哪些 schema 收集 email?") 與協調跨 schema 的欄位改名。
+    pub fn find_by_property(&self, prop: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .schema_sources
+            .iter()
+            .filter(|(_, source)| declares_property(source, prop))
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// 找出同一文件中從未被 `$ref` 引用的 `$defs`/`definitions` 條目, 返回其 JSON Pointer
+    ///
+    /// 拼錯的引用也會在這裡現形: 定義存在但引用處名稱不同, 定義就會顯示為未使用。
+    pub fn unused_defs(&self, name: &str) -> Vec<String> {
+        let Some(source) = self.schema_sources.get(name) else {
+            return Vec::new();
+        };
+
+        let mut defs = Vec::new();
+        let mut refs = Vec::new();
+        collect_defs_and_refs(source, "", &mut defs, &mut refs);
+
+        defs.retain(|pointer| {
+            let target = format!("#{}", pointer);
+            !refs.iter().any(|r| *r == target || r.starts_with(&format!("{}/", target)))
+        });
+        defs.sort();
+        defs
+    }
+
+    /// 匯出所有 schemas 的欄位為資料字典 CSV
+    ///
+    /// 欄位: `schema, field_path, type, required, description, sensitivity`;
+    /// 遞迴進入巢狀物件與陣列元素, `sensitivity` 取自 `x-sensitive`。
+    pub fn to_data_dictionary_csv(&self) -> String {
+        let mut rows = vec![[
+            "schema", "field_path", "type", "required", "description", "sensitivity",
+        ].map(String::from)];
+
+        let mut names: Vec<&String> = self.schema_sources.keys().collect();
+        names.sort();
+        for name in names {
+            collect_dictionary_rows(name, &self.schema_sources[name], "", &mut rows);
+        }
+
+        rows.iter()
+            .map(|row| row.iter().map(|cell| csv_escape(cell)).collect::<Vec<_>>().join(","))
+            .map(|line| line + "\n")
+            .collect()
+    }
+
+    /// 將 schema 的常見約束轉為白話規則 (用於需求文件)
+    ///
+    /// 如 `email is required`, `age must be an integer between 0 and 150`,
+    /// `status must be one of [active, inactive]`; schema 不存在時為空。
+    pub fn to_rules(&self, name: &str) -> Vec<String> {
+        let mut rules = Vec::new();
+        if let Some(source) = self.schema_sources.get(name) {
+            collect_rules(source, "", &mut rules);
+        }
+        rules
+    }
+
+    /// `auto_draft` 模式下偵測到的 draft
+    pub fn draft(&self, name: &str) -> Option<Draft> {
+        self.drafts.get(name).copied()
+    }
+
+    /// 編譯 schema 時實際使用的 draft: 明確設定的 draft, 否則為 `$schema` 宣告的版本,
+    /// 兩者皆無時為 jsonschema 的預設 draft
+    fn effective_draft(&self, name: &str) -> Draft {
+        self.draft(name)
+            .or_else(|| self.schema_sources.get(name).and_then(declared_draft))
+            .unwrap_or_default()
+    }
+
+    /// 載入時編譯失敗的 schemas (名稱 -> 錯誤訊息)
+    pub fn load_errors(&self) -> &HashMap<String, String> {
+        &self.load_errors
+    }
+
+    /// 找出第一個缺少的必填欄位, 返回其 instance path (如 `/address/city`)
+    ///
+    /// 每一層依 `required` 陣列的文件順序檢查 (JSON 陣列保留順序, 不受 `properties`
+    /// 物件鍵排序影響), 已存在的必填物件依同一順序遞迴; 之後才遞迴進入已存在的選填物件。
+    pub fn first_missing_required(&self, schema_name: &str, data: &Value) -> Option<String> {
+        let schema = self.schema_sources.get(schema_name)?;
+        first_missing_in(schema, data, "")
+    }
+
+    /// 數據中實際出現的非必填屬性 (點號路徑, 遞迴進入巢狀物件與陣列元素)
+    ///
+    /// 長期追蹤可看出客戶端實際使用哪些選填欄位, 作為棄用決策的依據。
+    pub fn present_optional_fields(&self, schema_name: &str, data: &Value) -> Vec<String> {
+        let Some(source) = self.schema_sources.get(schema_name) else {
+            return Vec::new();
+        };
+        let mut present = BTreeSet::new();
+        collect_present_optional(source, data, "", &mut present);
+        present.into_iter().collect()
+    }
+
+    /// 依 schema 型別轉換字串值後驗證, 成功時返回轉換後的數據
+    ///
+    /// 表單提交的值都是字串: `"1,5"` 依 `locale` 轉為 `1.5`, `"31/12/2024"`
+    /// (`format: date`) 轉為 `"2024-12-31"`, `"true"` 轉為 `true`。
+    /// `locale` 只影響這裡的轉換, `validate` 仍嚴格比對型別。
+    pub fn validate_coerced(
+        &self,
+        schema_name: &str,
+        data: &Value,
+        opts: &CoerceOptions,
+    ) -> Result<Value, Vec<String>> {
+        let source = self.schema_sources.get(schema_name)
+            .ok_or_else(|| vec![format!("Schema '{}' not found", schema_name)])?;
+
+        let mut coerced = data.clone();
+        coerce_value(source, &mut coerced, &opts.locale);
+        self.validate(schema_name, &coerced)?;
+        Ok(coerced)
+    }
+
+    /// 列出頂層 `oneOf`/`anyOf` 每個分支宣告的屬性名稱 (用於動態表單)
+    ///
+    /// 沒有頂層 union 的 schema 返回單一分組, 包含所有頂層屬性。
+    pub fn variant_fields(&self, name: &str) -> Vec<Vec<String>> {
+        let Some(source) = self.schema_sources.get(name) else {
+            return Vec::new();
+        };
+
+        let branches = source
+            .get("oneOf")
+            .or_else(|| source.get("anyOf"))
+            .and_then(Value::as_array);
+
+        match branches {
+            Some(branches) => branches.iter().map(property_names).collect(),
+            None => vec![property_names(source)],
+        }
+    }
+
+    /// 屬性路徑 (點號分隔) -> `const` 或單值 `enum` 的值
+    ///
+    /// 讓下游轉換程式直接取得標準常數, 不必再解析 schema。
+    pub fn constants(&self, name: &str) -> HashMap<String, Value> {
+        let mut constants = HashMap::new();
+        if let Some(source) = self.schema_sources.get(name) {
+            collect_constants(source, "", &mut constants);
+        }
+        constants
+    }
+
+    /// 返回填入 schema `default` 值後的數據 (遞迴進入巢狀物件與陣列)
+    pub fn apply_defaults(&self, schema_name: &str, data: &Value) -> Result<Value, Vec<String>> {
+        let source = self.schema_sources.get(schema_name)
+            .ok_or_else(|| vec![format!("Schema '{}' not found", schema_name)])?;
+
+        let mut filled = data.clone();
+        fill_defaults(source, &mut filled);
+        Ok(filled)
+    }
+
+    /// 移除 schema 未宣告的屬性後驗證, 成功時返回清理後的數據
+    ///
+    /// 宣告了 `patternProperties` 或 schema 形式 `additionalProperties` 的物件不會被清理。
+    pub fn validate_and_strip(&self, schema_name: &str, data: &Value) -> Result<Value, Vec<String>> {
+        self.validate_and_strip_to_depth(schema_name, data, None)
+    }
+
+    /// 同 `validate_and_strip`, 但只清理前 `max_depth` 層物件
+    ///
+    /// `Some(1)` 只清理頂層物件, `Some(2)` 再加上其直接子物件 (陣列不算一層),
+    /// 更深的巢狀物件保留原樣; None 表示不限深度。
+    pub fn validate_and_strip_to_depth(
+        &self,
+        schema_name: &str,
+        data: &Value,
+        max_depth: Option<usize>,
+    ) -> Result<Value, Vec<String>> {
+        let source = self.schema_sources.get(schema_name)
+            .ok_or_else(|| vec![format!("Schema '{}' not found", schema_name)])?;
+
+        let mut stripped = data.clone();
+        strip_unknown(source, &mut stripped, max_depth);
+        self.validate(schema_name, &stripped)?;
+        Ok(stripped)
+    }
+
+    /// 封閉世界驗證: schema 未宣告的屬性視為錯誤, 只檢查前 `max_depth` 層物件
+    ///
+    /// 深度語義同 `validate_and_strip_to_depth`; 超過深度的物件允許額外屬性,
+    /// 避免為了掃描深層大型內容付出成本。錯誤包含未知屬性與一般驗證錯誤。
+    pub fn validate_closed(&self, schema_name: &str, data: &Value, max_depth: Option<usize>) -> ValidationResult {
+        let Some(source) = self.schema_sources.get(schema_name) else {
+            return self.unknown_schema(schema_name);
+        };
+
+        let mut unknown = Vec::new();
+        find_unknown_properties(source, data, max_depth, "", &mut unknown);
+        let mut errors: Vec<String> = unknown
+            .into_iter()
+            .map(|path| format!("Unknown property at {}", path))
+            .collect();
+        if let Err(validation_errors) = self.validate(schema_name, data) {
+            errors.extend(validation_errors);
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    /// 驗證並返回正規化的文件: 依選項填入預設值、移除未知屬性, 最後排序所有鍵
+    ///
+    /// 結果與原始鍵順序和空白無關, 可直接用於雜湊、存儲與去重。
+    pub fn validate_normalize(
+        &self,
+        schema_name: &str,
+        data: &Value,
+        opts: NormalizeOptions,
+    ) -> Result<Value, Vec<String>> {
+        let source = self.schema_sources.get(schema_name)
+            .ok_or_else(|| vec![format!("Schema '{}' not found", schema_name)])?;
+
+        let mut normalized = data.clone();
+        if opts.apply_defaults {
+            fill_defaults(source, &mut normalized);
+        }
+        if opts.strip_unknown {
+            strip_unknown(source, &mut normalized, None);
+        }
+        if opts.fill_constants {
+            fill_constants(source, &mut normalized);
+        }
+        self.validate(schema_name, &normalized)?;
+        Ok(canonicalize(&normalized))
+    }
+}
+
+/// 遞迴填入缺少屬性的 `default` 值
+fn fill_defaults(schema: &Value, value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+                return;
+            };
+            for (name, subschema) in properties {
+                match object.get_mut(name) {
+                    Some(child) => fill_defaults(subschema, child),
+                    None => {
+                        if let Some(default) = subschema.get("default") {
+                            object.insert(name.clone(), default.clone());
+                        }
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for item in items.iter_mut() {
+                    fill_defaults(item_schema, item);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 物件是否接受未宣告的屬性 (宣告了 `patternProperties` 或 schema 形式的 `additionalProperties`)
+fn allows_extra_properties(schema: &Value) -> bool {
+    schema.get("patternProperties").is_some()
+        || schema.get("additionalProperties").is_some_and(Value::is_object)
+}
+
+/// schema 的 `const` 值, 或單值 `enum` 的唯一值
+fn constant_value(schema: &Value) -> Option<&Value> {
+    schema.get("const").or_else(|| match schema.get("enum").and_then(Value::as_array) {
+        Some(values) if values.len() == 1 => values.first(),
+        _ => None,
+    })
+}
+
+/// 遞迴收集所有常數屬性 (陣列元素沿用陣列的路徑)
+fn collect_constants(schema: &Value, prefix: &str, constants: &mut HashMap<String, Value>) {
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        for (name, subschema) in properties {
+            let path = join_path(prefix, name);
+            if let Some(value) = constant_value(subschema) {
+                constants.insert(path.clone(), value.clone());
+            }
+            collect_constants(subschema, &path, constants);
+        }
+    }
+    if let Some(item_schema) = schema.get("items") {
+        collect_constants(item_schema, prefix, constants);
+    }
+}
+
+/// 遞迴填入缺少的常數屬性
+fn fill_constants(schema: &Value, value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+                return;
+            };
+            for (name, subschema) in properties {
+                match object.get_mut(name) {
+                    Some(child) => fill_constants(subschema, child),
+                    None => {
+                        if let Some(constant) = constant_value(subschema) {
+                            object.insert(name.clone(), constant.clone());
+                        }
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for item in items.iter_mut() {
+                    fill_constants(item_schema, item);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 遞迴移除 `properties` 未宣告的屬性
+///
+/// `max_depth` 為處理的物件層數 (`Some(1)` 只處理頂層物件), None 表示不限。
+fn strip_unknown(schema: &Value, value: &mut Value, max_depth: Option<usize>) {
+    if max_depth == Some(0) {
+        return;
+    }
+    match value {
+        Value::Object(object) => {
+            let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+                return;
+            };
+            if !allows_extra_properties(schema) {
+                object.retain(|name, _| properties.contains_key(name));
+            }
+            let child_depth = max_depth.map(|depth| depth - 1);
+            for (name, child) in object.iter_mut() {
+                if let Some(subschema) = properties.get(name) {
+                    strip_unknown(subschema, child, child_depth);
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for item in items.iter_mut() {
+                    strip_unknown(item_schema, item, max_depth);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 遞迴收集 `properties` 未宣告屬性的 JSON Pointer, `max_depth` 語義同 `strip_unknown`
+fn find_unknown_properties(schema: &Value, value: &Value, max_depth: Option<usize>, path: &str, found: &mut Vec<String>) {
+    if max_depth == Some(0) {
+        return;
+    }
+    match value {
+        Value::Object(object) => {
+            let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+                return;
+            };
+            let closed = !allows_extra_properties(schema);
+            let child_depth = max_depth.map(|depth| depth - 1);
+            for (name, child) in object {
+                let child_path = format!("{}/{}", path, escape_pointer_segment(name));
+                match properties.get(name) {
+                    Some(subschema) => find_unknown_properties(subschema, child, child_depth, &child_path, found),
+                    None if closed => found.push(child_path),
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    find_unknown_properties(item_schema, item, max_depth, &format!("{}/{}", path, i), found);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// JSON 值的穩定雜湊 (FNV-1a 64-bit, 以 16 位十六進位表示)
+///
+/// 不使用 `DefaultHasher`: 其演算法不保證跨 Rust 版本穩定, 不適合寫入日誌。
+fn content_hash(value: &Value) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in value.to_string().bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// 將子 schema 附加到源的根 `allOf` (不存在時建立), 讓 `$defs`/`definitions` 仍留在根部
+fn with_all_of(source: &Value, subschema: &Value) -> Value {
+    let mut variant = source.clone();
+    if let Some(object) = variant.as_object_mut() {
+        let all_of = object.entry("allOf").or_insert_with(|| Value::Array(Vec::new()));
+        match all_of.as_array_mut() {
+            Some(items) => items.push(subschema.clone()),
+            None => *all_of = serde_json::json!([subschema]),
+        }
+    }
+    variant
+}
+
+/// 遞迴排序物件鍵, 產生與輸入順序無關的標準形式
+fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut entries: Vec<(&String, &Value)> = object.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, child)| (key.clone(), canonicalize(child)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+        other => other.clone(),
+    }
+}
+
+/// schema 源中 `x-tags` 陣列的字串標籤
+fn schema_tags(schema: &Value) -> Vec<String> {
+    schema
+        .get("x-tags")
+        .and_then(Value::as_array)
+        .map(|tags| tags.iter().filter_map(Value::as_str).map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// schema 的 `properties` 名稱列表
+fn property_names(schema: &Value) -> Vec<String> {
+    schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(|properties| properties.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// 屬性路徑: 以點號串接父路徑與屬性名
+fn join_path(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", prefix, name)
+    }
+}
+
+/// 遞迴列出 schema 宣告的屬性路徑與 enum 值 (陣列元素沿用陣列的路徑)
+fn collect_declared_fields(
+    schema: &Value,
+    prefix: &str,
+    properties: &mut BTreeSet<String>,
+    enums: &mut BTreeMap<String, BTreeSet<String>>,
+) {
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        if !prefix.is_empty() {
+            enums.insert(prefix.to_string(), values.iter().map(Value::to_string).collect());
+        }
+    }
+    if let Some(declared) = schema.get("properties").and_then(Value::as_object) {
+        for (name, subschema) in declared {
+            let path = join_path(prefix, name);
+            properties.insert(path.clone());
+            collect_declared_fields(subschema, &path, properties, enums);
+        }
+    }
+    if let Some(item_schema) = schema.get("items") {
+        collect_declared_fields(item_schema, prefix, properties, enums);
+    }
+}
+
+/// 遞迴記錄數據中出現的已宣告屬性與 enum 值
+fn record_seen_fields(schema: &Value, data: &Value, prefix: &str, seen: &mut CoverageAccumulator) {
+    if schema.get("enum").is_some() && !prefix.is_empty() {
+        seen.enum_values
+            .entry(prefix.to_string())
+            .or_default()
+            .insert(data.to_string());
+    }
+    match data {
+        Value::Object(object) => {
+            let Some(declared) = schema.get("properties").and_then(Value::as_object) else {
+                return;
+            };
+            for (name, child) in object {
+                if let Some(subschema) = declared.get(name) {
+                    let path = join_path(prefix, name);
+                    seen.properties.insert(path.clone());
+                    record_seen_fields(subschema, child, &path, seen);
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for item in items {
+                    record_seen_fields(item_schema, item, prefix, seen);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 在 OpenTelemetry span 中執行驗證 (需要 `otel` feature)
+///
+/// span 建立在調用者的當前 context 之下, 標記 `schema.name`、`schema.version`、
+/// `validation.result` 與 `validation.error_count`。
+#[cfg(feature = "otel")]
+fn with_validation_span(
+    schema_name: &str,
+    version: Option<&str>,
+    validate: impl FnOnce() -> ValidationResult,
+) -> ValidationResult {
+    use opentelemetry::trace::{Span, Status, Tracer};
+    use opentelemetry::{global, Context, KeyValue};
+
+    let tracer = global::tracer("schema-registry");
+    let mut span = tracer.start_with_context("schema.validate", &Context::current());
+    span.set_attribute(KeyValue::new("schema.name", schema_name.to_string()));
+    if let Some(version) = version {
+        span.set_attribute(KeyValue::new("schema.version", version.to_string()));
+    }
+
+    let result = validate();
+    let error_count = result.as_ref().err().map_or(0, Vec::len);
+    span.set_attribute(KeyValue::new("validation.result", if result.is_ok() { "valid" } else { "invalid" }));
+    span.set_attribute(KeyValue::new("validation.error_count", error_count as i64));
+    if error_count > 0 {
+        span.set_status(Status::error(format!("{} validation error(s)", error_count)));
+    }
+    span.end();
+    result
+}
+
+/// 結構驗證的所有錯誤 (未套用 lenient 降級與跨欄位規則)
+fn structural_details(schema: &JSONSchema, data: &Value) -> Vec<ValidationErrorDetail> {
+    if schema.is_valid(data) {
+        return Vec::new();
+    }
+    schema.iter_errors(data).map(|e| ValidationErrorDetail::from_error(&e)).collect()
+}
+
+/// 結構化錯誤轉為 `validate` 的錯誤訊息; schema 層級與跨欄位錯誤沒有數據位置, 只保留訊息
+fn error_messages(details: &[ValidationErrorDetail]) -> Vec<String> {
+    details
+        .iter()
+        .map(|detail| match detail.keyword.as_str() {
+            "schema" | "crossField" => detail.message.clone(),
+            _ => detail.to_string(),
+        })
+        .collect()
+}
+
+/// `with_lenient_enums` 可降級為警告的失敗
+fn is_enum_failure(detail: &ValidationErrorDetail) -> bool {
+    matches!(detail.keyword.as_str(), "enum" | "const")
+}
+
+/// schema 中任何層級的 `properties` 是否宣告了 `prop`
+fn declares_property(schema: &Value, prop: &str) -> bool {
+    match schema {
+        Value::Object(object) => {
+            let declared_here = object
+                .get("properties")
+                .and_then(Value::as_object)
+                .is_some_and(|properties| properties.contains_key(prop));
+            declared_here || object.values().any(|child| declares_property(child, prop))
+        }
+        Value::Array(items) => items.iter().any(|child| declares_property(child, prop)),
+        _ => false,
+    }
+}
+
+/// 跳脫 JSON Pointer 的一段 (`~` -> `~0`, `/` -> `~1`)
+fn escape_pointer_segment(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+/// 遞迴收集所有 `$defs`/`definitions` 條目的 pointer 與所有 `$ref` 值
+fn collect_defs_and_refs(schema: &Value, pointer: &str, defs: &mut Vec<String>, refs: &mut Vec<String>) {
+    match schema {
+        Value::Object(object) => {
+            for (key, child) in object {
+                let child_pointer = format!("{}/{}", pointer, escape_pointer_segment(key));
+                if key == "$ref" {
+                    if let Some(reference) = child.as_str() {
+                        refs.push(reference.to_string());
+                    }
+                } else if key == "$defs" || key == "definitions" {
+                    if let Some(entries) = child.as_object() {
+                        for def_name in entries.keys() {
+                            defs.push(format!("{}/{}", child_pointer, escape_pointer_segment(def_name)));
+                        }
+                    }
+                }
+                collect_defs_and_refs(child, &child_pointer, defs, refs);
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                collect_defs_and_refs(item, &format!("{}/{}", pointer, i), defs, refs);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 將數值邊界放寬 epsilon 後的 schema 副本
+///
+/// 不進入 `const`/`enum`/`default`/`examples`, 它們是數據而非 schema;
+/// draft 4 的布林 `exclusiveMinimum`/`exclusiveMaximum` 保持不變。
+fn relax_numeric_bounds(schema: &Value, epsilon: f64) -> Value {
+    match schema {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(key, child)| {
+                    let relaxed = match (key.as_str(), child.as_f64()) {
+                        ("minimum" | "exclusiveMinimum", Some(bound)) => {
+                            serde_json::Number::from_f64(bound - epsilon).map(Value::Number)
+                        }
+                        ("maximum" | "exclusiveMaximum", Some(bound)) => {
+                            serde_json::Number::from_f64(bound + epsilon).map(Value::Number)
+                        }
+                        ("const" | "enum" | "default" | "examples", _) => Some(child.clone()),
+                        _ => None,
+                    };
+                    (key.clone(), relaxed.unwrap_or_else(|| relax_numeric_bounds(child, epsilon)))
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(|item| relax_numeric_bounds(item, epsilon)).collect()),
+        other => other.clone(),
+    }
+}
+
+/// 遞迴移除值為 `null` 的物件屬性 (陣列中的 `null` 元素保留)
+fn remove_null_properties(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            object.retain(|_, child| !child.is_null());
+            object.values_mut().for_each(remove_null_properties);
+        }
+        Value::Array(items) => items.iter_mut().for_each(remove_null_properties),
+        _ => {}
+    }
+}
+
+/// 第一個超過 `max_bytes` 的字串值的 JSON Pointer
+fn find_oversized_string(value: &Value, max_bytes: usize, path: &str) -> Option<String> {
+    match value {
+        Value::String(text) if text.len() > max_bytes => Some(path.to_string()),
+        Value::Object(object) => object.iter().find_map(|(key, child)| {
+            find_oversized_string(child, max_bytes, &format!("{}/{}", path, escape_pointer_segment(key)))
+        }),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .find_map(|(i, child)| find_oversized_string(child, max_bytes, &format!("{}/{}", path, i))),
+        _ => None,
+    }
+}
+
+/// 遞迴產生資料字典的每一列
+fn collect_dictionary_rows(schema_name: &str, schema: &Value, prefix: &str, rows: &mut Vec<[String; 6]>) {
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        let required = required_names(schema);
+
+        for (name, subschema) in properties {
+            let path = join_path(prefix, name);
+            let type_name = match subschema.get("type") {
+                Some(Value::String(t)) => t.clone(),
+                Some(Value::Array(types)) => types
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join("|"),
+                _ => String::new(),
+            };
+            let sensitivity = match subschema.get("x-sensitive") {
+                Some(Value::String(level)) => level.clone(),
+                Some(Value::Bool(flag)) => flag.to_string(),
+                _ => String::new(),
+            };
+            rows.push([
+                schema_name.to_string(),
+                path.clone(),
+                type_name,
+                required.contains(&name.as_str()).to_string(),
+                subschema.get("description").and_then(Value::as_str).unwrap_or_default().to_string(),
+                sensitivity,
+            ]);
+            collect_dictionary_rows(schema_name, subschema, &path, rows);
+        }
+    }
+    if let Some(item_schema) = schema.get("items") {
+        collect_dictionary_rows(schema_name, item_schema, prefix, rows);
+    }
+}
+
+/// 遞迴收集欄位規則: 先列出該層的必填欄位, 再逐一描述屬性約束
+fn collect_rules(schema: &Value, prefix: &str, rules: &mut Vec<String>) {
+    for name in required_names(schema) {
+        rules.push(format!("{} is required", join_path(prefix, name)));
+    }
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        for (name, subschema) in properties {
+            let path = join_path(prefix, name);
+            rules.extend(describe_constraints(subschema).into_iter().map(|rule| format!("{} {}", path, rule)));
+            collect_rules(subschema, &path, rules);
+        }
+    }
+    if let Some(item_schema) = schema.get("items") {
+        collect_rules(item_schema, prefix, rules);
+    }
+}
+
+/// 單一屬性的約束描述 (不含欄位名), 如 `must be an integer between 0 and 150`
+fn describe_constraints(schema: &Value) -> Vec<String> {
+    let number = |keyword: &str| schema.get(keyword).filter(|v| v.is_number()).map(Value::to_string);
+    let mut phrase = Vec::new();
+
+    let type_names: Vec<&str> = match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !type_names.is_empty() {
+        let nouns: Vec<&str> = type_names
+            .iter()
+            .map(|t| match *t {
+                "integer" => "an integer",
+                "array" => "an array",
+                "object" => "an object",
+                "string" => "a string",
+                "number" => "a number",
+                "boolean" => "a boolean",
+                "null" => "null",
+                other => other,
+            })
+            .collect();
+        phrase.push(nouns.join(" or "));
+    }
+
+    match (number("minimum"), number("maximum")) {
+        (Some(min), Some(max)) => phrase.push(format!("between {} and {}", min, max)),
+        (Some(min), None) => phrase.push(format!("at least {}", min)),
+        (None, Some(max)) => phrase.push(format!("at most {}", max)),
+        (None, None) => {}
+    }
+    if let Some(min) = number("exclusiveMinimum") {
+        phrase.push(format!("greater than {}", min));
+    }
+    if let Some(max) = number("exclusiveMaximum") {
+        phrase.push(format!("less than {}", max));
+    }
+    match (number("minLength"), number("maxLength")) {
+        (Some(min), Some(max)) => phrase.push(format!("between {} and {} characters long", min, max)),
+        (Some(min), None) => phrase.push(format!("at least {} characters long", min)),
+        (None, Some(max)) => phrase.push(format!("at most {} characters long", max)),
+        (None, None) => {}
+    }
+
+    let mut rules = Vec::new();
+    if !phrase.is_empty() {
+        rules.push(format!("must be {}", phrase.join(" ")));
+    }
+    if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+        rules.push(format!("must match pattern {}", pattern));
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        let values: Vec<String> = values
+            .iter()
+            .map(|v| v.as_str().map(String::from).unwrap_or_else(|| v.to_string()))
+            .collect();
+        rules.push(format!("must be one of [{}]", values.join(", ")));
+    }
+    rules
+}
+
+/// `enum` 建議值允許的最大編輯距離
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// 在 `enum` 允許值中找出與提交字串編輯距離最小者 (超過門檻時為 None)
+fn suggest_enum_value(allowed: &Value, submitted: &Value) -> Option<String> {
+    let submitted = submitted.as_str()?;
+    allowed
+        .as_array()?
+        .iter()
+        .filter_map(Value::as_str)
+        .map(|candidate| (edit_distance(submitted, candidate), candidate))
+        .filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+/// Levenshtein 編輯距離 (以字元計)
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// 依 RFC 4180 跳脫 CSV 欄位
+fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// 檢查所有本地 `$ref` 鏈不超過 `max_depth` 且沒有純引用循環
+fn check_ref_chains(schema: &Value, max_depth: usize) -> Result<(), String> {
+    let mut defs = Vec::new();
+    let mut refs = Vec::new();
+    collect_defs_and_refs(schema, "", &mut defs, &mut refs);
+
+    for start in refs.iter().filter(|r| r.starts_with('#')) {
+        let mut chain = vec![start.as_str()];
+        let mut current = start.as_str();
+        while let Some(next) = schema
+            .pointer(&current[1..])
+            .and_then(|target| target.get("$ref"))
+            .and_then(Value::as_str)
+            .filter(|next| next.starts_with('#'))
+        {
+            let cycle = chain.contains(&next);
+            chain.push(next);
+            if cycle {
+                return Err(format!("Reference cycle: {}", chain.join(" -> ")));
+            }
+            if chain.len() > max_depth {
+                return Err(format!(
+                    "Reference chain exceeds max_ref_depth {}: {}",
+                    max_depth,
+                    chain.join(" -> ")
+                ));
+            }
+            current = next;
+        }
+    }
+    Ok(())
+}
+
+/// 數據文件中的節點總數 (包含自身)
+fn count_nodes(value: &Value) -> usize {
+    1 + match value {
+        Value::Object(object) => object.values().map(count_nodes).sum(),
+        Value::Array(items) => items.iter().map(count_nodes).sum(),
+        _ => 0,
+    }
+}
+
+/// 依 schema 宣告的型別遞迴轉換字串值
+fn coerce_value(schema: &Value, value: &mut Value, locale: &Locale) {
+    match value {
+        Value::String(text) => {
+            if let Some(coerced) = coerce_string(schema, text, locale) {
+                *value = coerced;
+            }
+        }
+        Value::Object(object) => {
+            if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+                for (name, child) in object.iter_mut() {
+                    if let Some(subschema) = properties.get(name) {
+                        coerce_value(subschema, child, locale);
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for item in items.iter_mut() {
+                    coerce_value(item_schema, item, locale);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// schema 的 `type` 是否包含指定型別 (支持字串或陣列)
+fn schema_allows_type(schema: &Value, type_name: &str) -> bool {
+    match schema.get("type") {
+        Some(Value::String(t)) => t == type_name,
+        Some(Value::Array(types)) => types.iter().any(|t| t.as_str() == Some(type_name)),
+        _ => false,
+    }
+}
+
+fn coerce_string(schema: &Value, text: &str, locale: &Locale) -> Option<Value> {
+    if schema_allows_type(schema, "string") {
+        if schema.get("format").and_then(Value::as_str) == Some("date") {
+            return parse_localized_date(text, locale.date_order).map(Value::String);
+        }
+        return None;
+    }
+    if schema_allows_type(schema, "integer") || schema_allows_type(schema, "number") {
+        let number = parse_localized_number(text, locale).filter(|number| number.is_finite())?;
+        // `as i64` 會飽和, 超出 i64 範圍的整數不轉換, 避免悄悄改寫數據
+        let in_i64_range = number >= i64::MIN as f64 && number < i64::MAX as f64;
+        if schema_allows_type(schema, "integer") && number.fract() == 0.0 && in_i64_range {
+            return Some(Value::from(number as i64));
+        }
+        if !schema_allows_type(schema, "number") && number.fract() == 0.0 {
+            return None;
+        }
+        return serde_json::Number::from_f64(number).map(Value::Number);
+    }
+    if schema_allows_type(schema, "boolean") {
+        return match text.trim() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        };
+    }
+    None
+}
+
+/// 依地區格式解析數字; 千分位符號只接受在小數點左側、每三位一組的位置
+///
+/// 位置不符 (如歐洲格式的 `1.5`、`1.2.3`) 時返回 None, 保留原字串交由驗證報錯。
+fn parse_localized_number(text: &str, locale: &Locale) -> Option<f64> {
+    let text = text.trim();
+    let (integer, fraction) = match text.split_once(locale.decimal_separator) {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (text, None),
+    };
+
+    let integer = match locale.thousands_separator {
+        Some(separator) if integer.contains(separator) => {
+            let digits = integer.trim_start_matches(['-', '+']);
+            let mut groups = digits.split(separator);
+            let leading = groups.next().unwrap_or_default();
+            let grouped = (1..=3).contains(&leading.len())
+                && groups.all(|group| group.len() == 3)
+                && digits.chars().all(|c| c.is_ascii_digit() || c == separator);
+            if !grouped {
+                return None;
+            }
+            integer.replace(separator, "")
+        }
+        _ => integer.to_string(),
+    };
+
+    let normalized = match fraction {
+        Some(fraction) => format!("{}.{}", integer, fraction),
+        None => integer,
+    };
+    normalized.parse().ok()
+}
+
+/// 轉為 ISO 8601 日期 (`YYYY-MM-DD`), 無法解析時返回 None
+fn parse_localized_date(text: &str, order: DateOrder) -> Option<String> {
+    let parts: Vec<u32> = text
+        .trim()
+        .split(['/', '.', '-'])
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let [a, b, c] = parts[..] else { return None };
+
+    let (year, month, day) = match order {
+        DateOrder::Ymd => (a, b, c),
+        DateOrder::Dmy => (c, b, a),
+        DateOrder::Mdy => (c, a, b),
+    };
+    if year < 1000 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+/// schema 的 `required` 名稱列表
+fn required_names(schema: &Value) -> Vec<&str> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+/// 遞迴收集數據中出現的非必填屬性路徑
+fn collect_present_optional(schema: &Value, data: &Value, prefix: &str, present: &mut BTreeSet<String>) {
+    match data {
+        Value::Object(object) => {
+            let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+                return;
+            };
+            let required = required_names(schema);
+
+            for (name, child) in object {
+                let Some(subschema) = properties.get(name) else {
+                    continue;
+                };
+                let path = join_path(prefix, name);
+                if !required.contains(&name.as_str()) {
+                    present.insert(path.clone());
+                }
+                collect_present_optional(subschema, child, &path, present);
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for item in items {
+                    collect_present_optional(item_schema, item, prefix, present);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 遞迴尋找第一個缺少的必填屬性
+fn first_missing_in(schema: &Value, data: &Value, path: &str) -> Option<String> {
+    let object = data.as_object()?;
+    let required = required_names(schema);
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let child = |name: &str| -> Option<String> {
+        let child_path = format!("{}/{}", path, escape_pointer_segment(name));
+        match object.get(name) {
+            None => Some(child_path),
+            Some(value) => first_missing_in(properties?.get(name)?, value, &child_path),
+        }
+    };
+
+    if let Some(found) = required.iter().find_map(|name| child(name)) {
+        return Some(found);
+    }
+    properties?
+        .keys()
+        .filter(|name| !required.contains(&name.as_str()) && object.contains_key(*name))
+        .find_map(|name| child(name))
+}
+
+/// `Sanitizer` 的清理步驟
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SanitizeStep {
+    /// 依 schema 型別轉換字串值 (見 `validate_coerced`)
+    Coerce,
+    /// 填入 `default` 值 (見 `apply_defaults`)
+    ApplyDefaults,
+    /// 移除未宣告的屬性 (見 `validate_and_strip`)
+    StripUnknown,
+}
+
+/// `Sanitizer` 的選項
+#[derive(Debug, Clone)]
+pub struct SanitizeOptions {
+    /// 依序執行的步驟; 驗證總是在所有步驟之後執行
+    pub steps: Vec<SanitizeStep>,
+    /// `Coerce` 步驟使用的選項
+    pub coerce: CoerceOptions,
+}
+
+impl Default for SanitizeOptions {
+    fn default() -> Self {
+        Self {
+            steps: vec![SanitizeStep::Coerce, SanitizeStep::ApplyDefaults, SanitizeStep::StripUnknown],
+            coerce: CoerceOptions::default(),
+        }
+    }
+}
+
+/// 入站數據的清理流程: 依序執行配置的步驟, 最後驗證
+pub struct Sanitizer {
+    opts: SanitizeOptions,
+}
+
+impl Sanitizer {
+    pub fn new(opts: SanitizeOptions) -> Self {
+        Self { opts }
+    }
+
+    /// 清理並驗證數據, 成功時返回清理後的數據
+    pub fn process(&self, registry: &SchemaRegistry, schema_name: &str, data: &Value) -> Result<Value, Vec<String>> {
+        let source = registry.get_schema_source(schema_name)
+            .ok_or_else(|| vec![format!("Schema '{}' not found", schema_name)])?;
+
+        let mut sanitized = data.clone();
+        for step in &self.opts.steps {
+            match step {
+                SanitizeStep::Coerce => coerce_value(source, &mut sanitized, &self.opts.coerce.locale),
+                SanitizeStep::ApplyDefaults => fill_defaults(source, &mut sanitized),
+                SanitizeStep::StripUnknown => strip_unknown(source, &mut sanitized, None),
+            }
+        }
+
+        registry.validate(schema_name, &sanitized)?;
+        Ok(sanitized)
+    }
+}
+
+/// 依文件副檔名選擇 schema 的路由 (用於文件上傳流程)
+pub struct ExtensionRouter<'a> {
+    registry: &'a SchemaRegistry,
+    routes: HashMap<String, String>,
+    default_schema: Option<String>,
+}
+
+impl<'a> ExtensionRouter<'a> {
+    pub fn new(registry: &'a SchemaRegistry) -> Self {
+        Self {
+            registry,
+            routes: HashMap::new(),
+            default_schema: None,
+        }
+    }
+
+    /// 將副檔名 (不分大小寫, 可帶或不帶 `.`) 對應到 schema
+    pub fn route(mut self, extension: &str, schema_name: &str) -> Self {
+        self.routes.insert(normalize_extension(extension), schema_name.to_string());
+        self
+    }
+
+    /// 未對應的副檔名改用此 schema; 未設定時返回錯誤
+    pub fn with_default(mut self, schema_name: &str) -> Self {
+        self.default_schema = Some(schema_name.to_string());
+        self
+    }
+
+    /// 文件名對應的 schema 名稱
+    pub fn schema_for(&self, filename: &str) -> Option<&str> {
+        Path::new(filename)
+            .extension()
+            .and_then(|s| s.to_str())
+            .and_then(|ext| self.routes.get(&normalize_extension(ext)))
+            .or(self.default_schema.as_ref())
+            .map(String::as_str)
+    }
+
+    /// 以文件名對應的 schema 驗證數據
+    pub fn validate_file(&self, filename: &str, data: &Value) -> ValidationResult {
+        let schema_name = self.schema_for(filename)
+            .ok_or_else(|| vec![format!("No schema mapped for file '{}'", filename)])?;
+        self.registry.validate(schema_name, data)
+    }
+}
+
+/// 逐欄位驗證 multipart 表單的驗證器 (用於文件 + metadata 上傳流程)
+///
+/// 每個欄位的內容解析為 JSON 後以對應的 schema 驗證, 欄位到達即可驗證而不需緩衝整個表單。
+pub struct MultipartValidator<'a> {
+    registry: &'a SchemaRegistry,
+    fields: HashMap<String, String>,
+    reject_unmapped: bool,
+}
+
+impl<'a> MultipartValidator<'a> {
+    pub fn new(registry: &'a SchemaRegistry) -> Self {
+        Self {
+            registry,
+            fields: HashMap::new(),
+            reject_unmapped: false,
+        }
+    }
+
+    /// 將表單欄位對應到 schema
+    pub fn field(mut self, field_name: &str, schema_name: &str) -> Self {
+        self.fields.insert(field_name.to_string(), schema_name.to_string());
+        self
+    }
+
+    /// 拒絕未對應 schema 的欄位; 預設直接通過 (如文件內容)
+    pub fn reject_unmapped(mut self, enabled: bool) -> Self {
+        self.reject_unmapped = enabled;
+        self
+    }
+
+    /// 驗證單一欄位的內容
+    pub fn validate_field(&self, field_name: &str, body: &[u8]) -> ValidationResult {
+        let Some(schema_name) = self.fields.get(field_name) else {
+            return if self.reject_unmapped {
+                Err(vec![format!("No schema mapped for field '{}'", field_name)])
+            } else {
+                Ok(())
+            };
+        };
+        let data: Value = serde_json::from_slice(body)
+            .map_err(|e| vec![format!("Invalid JSON in field '{}': {}", field_name, e)])?;
+        self.registry.validate(schema_name, &data)
+    }
+
+    /// 依到達順序逐一驗證欄位, 每取得一個欄位即產生其結果
+    pub fn validate_stream<I, B>(&self, fields: I) -> impl Iterator<Item = (String, ValidationResult)> + '_
+    where
+        I: IntoIterator<Item = (String, B)>,
+        I::IntoIter: 'a,
+        B: AsRef<[u8]>,
+    {
+        fields.into_iter().map(move |(name, body)| {
+            let result = self.validate_field(&name, body.as_ref());
+            (name, result)
+        })
+    }
+}
+
+fn normalize_extension(extension: &str) -> String {
+    extension.trim_start_matches('.').to_ascii_lowercase()
+}
+
+/// 兩個 schema 版本之間的相容性 (Confluent Schema Registry 語義)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compatibility {
+    /// 新舊雙向相容
+    Full,
+    /// 新 schema 能接受舊數據 (如移除必填欄位)
+    Backward,
+    /// 舊 schema 能接受新數據 (如新增必填欄位)
+    Forward,
+    /// 雙向都不相容 (如型別變更)
+    Breaking,
+}
+
+/// 兩個 schema 之間的結構差異 (屬性以點號路徑表示)
+#[derive(Debug, Default)]
+struct SchemaChanges {
+    /// 新版本中變為必填的屬性 (舊數據可能缺少)
+    added_required: Vec<String>,
+    /// 新版本中不再必填或被移除的必填屬性 (新數據可能缺少)
+    removed_required: Vec<String>,
+    type_changes: Vec<String>,
+}
+
+/// 遞迴比較兩個 schema 的必填欄位與型別
+fn compare_schemas(old: &Value, new: &Value, prefix: &str, changes: &mut SchemaChanges) {
+    let old_required = required_names(old);
+    let new_required = required_names(new);
+    for name in &new_required {
+        if !old_required.contains(name) {
+            changes.added_required.push(join_path(prefix, name));
+        }
+    }
+    for name in &old_required {
+        if !new_required.contains(name) {
+            changes.removed_required.push(join_path(prefix, name));
+        }
+    }
+
+    let old_properties = old.get("properties").and_then(Value::as_object);
+    let new_properties = new.get("properties").and_then(Value::as_object);
+    let (Some(old_properties), Some(new_properties)) = (old_properties, new_properties) else {
+        return;
+    };
+    for (name, old_schema) in old_properties {
+        let Some(new_schema) = new_properties.get(name) else {
+            continue;
+        };
+        let path = join_path(prefix, name);
+        if old_schema.get("type") != new_schema.get("type") {
+            changes.type_changes.push(path.clone());
+        }
+        compare_schemas(old_schema, new_schema, &path, changes);
+    }
+}
+
+/// JSON Patch (RFC 6902) 操作, 路徑為 JSON Pointer
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOp {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Move { from: String, path: String },
+}
+
+impl PatchOp {
+    /// 轉換為 JSON Patch 文件中的一個操作
+    pub fn to_json(&self) -> Value {
+        match self {
+            PatchOp::Add { path, value } => serde_json::json!({"op": "add", "path": path, "value": value}),
+            PatchOp::Remove { path } => serde_json::json!({"op": "remove", "path": path}),
+            PatchOp::Move { from, path } => serde_json::json!({"op": "move", "from": from, "path": path}),
+        }
+    }
+}
+
+/// 支持多版本的 Schema Registry
+pub struct VersionedRegistry {
+    versions: HashMap<String, SchemaRegistry>,
+    default_version: String,
+    /// schema 名稱 -> 欄位改名 (舊點號路徑, 新點號路徑), 供 `upgrade_hint` 使用
+    field_renames: HashMap<String, Vec<(String, String)>>,
+}
+
+impl VersionedRegistry {
+    /// 創建多版本 registry
+    pub fn new(base_path: &str, versions: &[&str]) -> Result<Self, std::io::Error> {
+        let mut registries = HashMap::new();
+
+        for version in versions {
+            let path = format!("{}/{}", base_path, version);
+            match SchemaRegistry::from_directory(&path) {
+                Ok(mut registry) => {
+                    registry.version = Some(version.to_string());
+                    println!("📦 Loaded version: {}", version);
+                    registries.insert(version.to_string(), registry);
+                }
+                Err(e) => {
+                    eprintln!("⚠️  Failed to load version {}: {}", version, e);
+                }
+            }
+        }
+
+        let default_version = versions.last()
+            .ok_or_else(|| std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "At least one version required"
+            ))?
+            .to_string();
+
+        Ok(Self {
+            versions: registries,
+            default_version,
+            field_renames: HashMap::new(),
+        })
+    }
+
+    /// 記錄 schema 在版本間的欄位改名 (點號路徑, 如 `customer.mail` -> `customer.email`)
+    pub fn with_field_rename(mut self, schema_name: &str, from: &str, to: &str) -> Self {
+        self.field_renames
+            .entry(schema_name.to_string())
+            .or_default()
+            .push((from.to_string(), to.to_string()));
+        self
+    }
+
+    /// 比較兩個版本的 schema, 建議把 `from` 版本的數據推向 `to` 版本的 JSON Patch
+    ///
+    /// 依序產生: 已設定的欄位改名 (`move`)、新必填欄位 (`add`, 使用 `default`/`const`
+    /// 或型別的空值)、`to` 版本封閉物件中已移除的欄位 (`remove`)。結果只是遷移的起點,
+    /// 不保證套用後即通過驗證; 任一版本缺少該 schema 時返回空列表。
+    pub fn upgrade_hint(&self, data: &Value, from: &str, to: &str, name: &str) -> Vec<PatchOp> {
+        let source = |version: &str| {
+            self.versions.get(version).and_then(|registry| registry.get_schema_source(name))
+        };
+        let (Some(old_source), Some(new_source)) = (source(from), source(to)) else {
+            return Vec::new();
+        };
+
+        let mut ops = Vec::new();
+        let mut patched = data.clone();
+        for (old_path, new_path) in self.field_renames.get(name).into_iter().flatten() {
+            let (from_pointer, to_pointer) = (dotted_to_pointer(old_path), dotted_to_pointer(new_path));
+            if patched.pointer(&to_pointer).is_some() {
+                continue;
+            }
+            if let Some(value) = take_pointer(&mut patched, &from_pointer) {
+                if insert_pointer(&mut patched, &to_pointer, value.clone()) {
+                    ops.push(PatchOp::Move { from: from_pointer, path: to_pointer });
+                } else {
+                    insert_pointer(&mut patched, &from_pointer, value);
+                }
+            }
+        }
+
+        collect_upgrade_ops(Some(old_source), new_source, &patched, "", &mut ops);
+        ops
+    }
+
+    /// 使用指定版本驗證
+    pub fn validate(&self, version: Option<&str>, schema_name: &str, data: &Value) -> ValidationResult {
+        let version = version.unwrap_or(&self.default_version);
+
+        let registry = self.versions.get(version)
+            .ok_or_else(|| vec![format!("Version '{}' not found", version)])?;
+
+        registry.validate(schema_name, data)
+    }
+
+    /// 列出所有版本
+    pub fn list_versions(&self) -> Vec<&String> {
+        self.versions.keys().collect()
+    }
+
+    /// 判斷 schema 從 `old` 版本到 `new` 版本的相容性等級
+    ///
+    /// 依必填欄位的增減與型別變更推導: 新增必填破壞 backward, 移除必填破壞 forward,
+    /// 型別變更兩者皆破壞。任一版本缺少該 schema 時視為 `Breaking`。
+    pub fn compatibility(&self, old: &str, new: &str, name: &str) -> Compatibility {
+        let source = |version: &str| {
+            self.versions.get(version).and_then(|registry| registry.get_schema_source(name))
+        };
+        let (Some(old_source), Some(new_source)) = (source(old), source(new)) else {
+            return Compatibility::Breaking;
+        };
+
+        let mut changes = SchemaChanges::default();
+        compare_schemas(old_source, new_source, "", &mut changes);
+
+        let types_stable = changes.type_changes.is_empty();
+        let backward = types_stable && changes.added_required.is_empty();
+        let forward = types_stable && changes.removed_required.is_empty();
+        match (backward, forward) {
+            (true, true) => Compatibility::Full,
+            (true, false) => Compatibility::Backward,
+            (false, true) => Compatibility::Forward,
+            (false, false) => Compatibility::Breaking,
+        }
+    }
+}
+
+/// 環境分層的 Schema Registry: overlay (如 `staging`, `prod`) 只包含與 base 的差異
+///
+/// 解析 schema 時依序檢查啟用中的 overlays, 最後才使用 base; 各層保持獨立,
+/// 可在執行期調整啟用的 overlays 而不需重新載入。
+pub struct LayeredRegistry {
+    base: SchemaRegistry,
+    overlays: HashMap<String, SchemaRegistry>,
+    /// 啟用中的 overlay 名稱 (優先度高到低)
+    active: Vec<String>,
+}
+
+impl LayeredRegistry {
+    /// 創建分層 registry, `overlays` 依優先度高到低排列且全部啟用
+    pub fn new(base: SchemaRegistry, overlays: Vec<(String, SchemaRegistry)>) -> Self {
+        let active = overlays.iter().map(|(name, _)| name.clone()).collect();
+        Self {
+            base,
+            overlays: overlays.into_iter().collect(),
+            active,
+        }
+    }
+
+    /// 設定啟用的 overlays (優先度高到低), 未知的 overlay 名稱返回錯誤
+    pub fn set_active(&mut self, names: &[&str]) -> Result<(), String> {
+        if let Some(unknown) = names.iter().find(|name| !self.overlays.contains_key(**name)) {
+            return Err(format!("Overlay '{}' not found", unknown));
+        }
+        self.active = names.iter().map(|name| name.to_string()).collect();
+        Ok(())
+    }
+
+    /// 啟用中的 overlay 名稱 (優先度高到低)
+    pub fn active_overlays(&self) -> Vec<&str> {
+        self.active.iter().map(String::as_str).collect()
+    }
+
+    /// 提供 schema 的層名稱, base 層返回 `"base"`
+    pub fn layer_of(&self, schema_name: &str) -> Option<&str> {
+        self.active
+            .iter()
+            .find(|layer| self.overlays[*layer].schemas.contains_key(schema_name))
+            .map(String::as_str)
+            .or_else(|| self.base.schemas.contains_key(schema_name).then_some("base"))
+    }
+
+    /// 解析 schema 所在的 registry, 找不到時退回 base
+    fn resolve(&self, schema_name: &str) -> &SchemaRegistry {
+        self.active
+            .iter()
+            .map(|layer| &self.overlays[layer])
+            .find(|registry| registry.schemas.contains_key(schema_name))
+            .unwrap_or(&self.base)
+    }
+
+    /// 使用解析出的 schema 驗證數據
+    pub fn validate(&self, schema_name: &str, data: &Value) -> ValidationResult {
+        self.resolve(schema_name).validate(schema_name, data)
+    }
+}
+
+/// 依 `Accept-Version` header 選擇版本的路由, 統一各 handler 的版本解析策略
+pub struct HeaderVersionRouter<'a> {
+    registry: &'a VersionedRegistry,
+    /// header 值 (小寫) -> 版本, 如 `latest` -> `v3`
+    aliases: HashMap<String, String>,
+    fallback_to_default: bool,
+}
+
+impl<'a> HeaderVersionRouter<'a> {
+    pub fn new(registry: &'a VersionedRegistry) -> Self {
+        Self {
+            registry,
+            aliases: HashMap::new(),
+            fallback_to_default: false,
+        }
+    }
+
+    /// 將 header 值 (不分大小寫) 對應到版本
+    pub fn alias(mut self, header_value: &str, version: &str) -> Self {
+        self.aliases.insert(header_value.trim().to_lowercase(), version.to_string());
+        self
+    }
+
+    /// 未知的 header 值改用預設版本; 未啟用時返回錯誤
+    pub fn fallback_to_default(mut self, enabled: bool) -> Self {
+        self.fallback_to_default = enabled;
+        self
+    }
+
+    /// header 對應的版本, 未帶 header 時為預設版本
+    pub fn version_for(&self, header: Option<&str>) -> Option<&str> {
+        let Some(header) = header.map(str::trim).filter(|h| !h.is_empty()) else {
+            return Some(&self.registry.default_version);
+        };
+        let version = self
+            .aliases
+            .get(&header.to_lowercase())
+            .map(String::as_str)
+            .unwrap_or(header);
+        match self.registry.versions.get_key_value(version) {
+            Some((version, _)) => Some(version),
+            None if self.fallback_to_default => Some(&self.registry.default_version),
+            None => None,
+        }
+    }
+
+    /// 以 header 對應版本的 schema 驗證數據
+    pub fn validate(&self, header: Option<&str>, schema_name: &str, data: &Value) -> ValidationResult {
+        let version = self.version_for(header)
+            .ok_or_else(|| vec![format!("Unsupported Accept-Version '{}'", header.unwrap_or_default())])?;
+        self.registry.validate(Some(version), schema_name, data)
+    }
+}
+
+/// 點號路徑轉為 JSON Pointer (`a.b` -> `/a/b`)
+fn dotted_to_pointer(path: &str) -> String {
+    path.split('.').map(|segment| format!("/{}", escape_pointer_segment(segment))).collect()
+}
+
+/// 拆分 JSON Pointer 為父路徑與最後一段 (未轉義)
+fn split_pointer(pointer: &str) -> Option<(&str, String)> {
+    let (parent, last) = pointer.rsplit_once('/')?;
+    Some((parent, last.replace("~1", "/").replace("~0", "~")))
+}
+
+/// 移除並返回 JSON Pointer 指向的物件欄位
+fn take_pointer(value: &mut Value, pointer: &str) -> Option<Value> {
+    let (parent, key) = split_pointer(pointer)?;
+    value.pointer_mut(parent)?.as_object_mut()?.remove(&key)
+}
+
+/// 在 JSON Pointer 位置插入欄位, 父物件不存在時返回 false
+fn insert_pointer(value: &mut Value, pointer: &str, field: Value) -> bool {
+    let Some((parent, key)) = split_pointer(pointer) else {
+        return false;
+    };
+    match value.pointer_mut(parent).and_then(Value::as_object_mut) {
+        Some(object) => {
+            object.insert(key, field);
+            true
+        }
+        None => false,
+    }
+}
+
+/// 新必填欄位的建議值: `default`、`const`, 否則為宣告型別的空值
+fn placeholder_value(schema: &Value) -> Value {
+    if let Some(value) = schema.get("default").or_else(|| schema.get("const")) {
+        return value.clone();
+    }
+    match schema.get("type").and_then(Value::as_str) {
+        Some("string") => Value::String(String::new()),
+        Some("integer") | Some("number") => Value::from(0),
+        Some("boolean") => Value::Bool(false),
+        Some("array") => Value::Array(Vec::new()),
+        Some("object") => Value::Object(serde_json::Map::new()),
+        _ => Value::Null,
+    }
+}
+
+/// 遞迴收集把數據推向 `new` schema 的 `add`/`remove` 操作
+fn collect_upgrade_ops(old: Option<&Value>, new: &Value, data: &Value, pointer: &str, ops: &mut Vec<PatchOp>) {
+    let Some(object) = data.as_object() else {
+        return;
+    };
+    let old_properties = old.and_then(|old| old.get("properties")).and_then(Value::as_object);
+    let new_properties = new.get("properties").and_then(Value::as_object);
+    let child_pointer = |name: &str| format!("{}/{}", pointer, escape_pointer_segment(name));
+
+    for name in required_names(new) {
+        if !object.contains_key(name) {
+            let value = new_properties
+                .and_then(|properties| properties.get(name))
+                .map(placeholder_value)
+                .unwrap_or(Value::Null);
+            ops.push(PatchOp::Add { path: child_pointer(name), value });
+        }
+    }
+
+    if new.get("additionalProperties") == Some(&Value::Bool(false)) {
+        for name in old_properties.into_iter().flat_map(|properties| properties.keys()) {
+            let still_declared = new_properties.is_some_and(|properties| properties.contains_key(name));
+            if object.contains_key(name) && !still_declared {
+                ops.push(PatchOp::Remove { path: child_pointer(name) });
+            }
+        }
+    }
+
+    for (name, child) in new_properties.into_iter().flatten() {
+        if let Some(value) = object.get(name) {
+            let old_child = old_properties.and_then(|properties| properties.get(name));
+            collect_upgrade_ops(old_child, child, value, &child_pointer(name), ops);
+        }
+    }
+}
+
+/// 支持熱重載的 Schema Registry
+pub struct HotReloadRegistry {
+    registry: Arc<RwLock<SchemaRegistry>>,
+    /// 重載時讀取的來源; 由 channel 推送更新時為 None
+    source: Option<Arc<dyn SchemaSource + Send + Sync>>,
+    /// 每次重載沿用的載入選項
+    builder: Arc<RegistryBuilder>,
+    /// 監聽文件變化的 schema 目錄; 非目錄來源時為 None
+    #[cfg_attr(not(feature = "watch"), allow(dead_code))]
+    path: Option<String>,
+}
+
+impl HotReloadRegistry {
+    /// 創建支持熱重載的 registry
+    pub fn new(path: &str) -> Result<Self, std::io::Error> {
+        Self::with_builder(path, RegistryBuilder::default())
+    }
+
+    /// 創建以指定載入選項 (轉換、draft、base URI...) 載入與重載目錄的 registry
+    pub fn with_builder(path: &str, builder: RegistryBuilder) -> Result<Self, std::io::Error> {
+        let source = DirectorySource::new(path).with_format_precedence(&builder.format_precedence);
+        let mut hot = Self::from_source_with_builder(source, builder)?;
+        hot.path = Some(path.to_string());
+        Ok(hot)
+    }
+
+    /// 創建從任意 `SchemaSource` 重載的 registry
+    pub fn from_source<S: SchemaSource + Send + Sync + 'static>(source: S) -> Result<Self, SourceError> {
+        Self::from_source_with_builder(source, RegistryBuilder::default())
+    }
+
+    /// 創建以指定載入選項從任意 `SchemaSource` 載入與重載的 registry
+    pub fn from_source_with_builder<S: SchemaSource + Send + Sync + 'static>(
+        source: S,
+        builder: RegistryBuilder,
+    ) -> Result<Self, SourceError> {
+        let registry = Arc::new(RwLock::new(builder.load_source(&source)?));
+
+        Ok(Self {
+            registry,
+            source: Some(Arc::new(source)),
+            builder: Arc::new(builder),
+            path: None,
+        })
+    }
+
+    /// 創建由 channel 推送更新的 registry (用於測試與控制平面)
+    ///
+    /// 收到新 registry 時原子替換; 在第一次推送前 registry 為空。
+    /// 所有 sender 被 drop 後停止接收更新。
+    pub fn from_channel(rx: Receiver<SchemaRegistry>) -> Self {
+        let registry = Arc::new(RwLock::new(SchemaRegistry::empty()));
+
+        let target = Arc::clone(&registry);
+        std::thread::spawn(move || {
+            for new_registry in rx {
+                swap_registry(&target, new_registry);
+                println!("🔄 Schemas updated from channel");
+            }
+        });
+
+        Self {
+            registry,
+            source: None,
+            builder: Arc::new(RegistryBuilder::default()),
+            path: None,
+        }
+    }
+
+    /// 從來源載入新的 registry, channel 模式下返回錯誤
+    fn load_fresh(&self) -> Result<SchemaRegistry, std::io::Error> {
+        let source = self.source.as_deref().ok_or_else(|| std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Registry is fed from a channel, not a source",
+        ))?;
+        Ok(self.builder.load_source(source)?)
+    }
+
+    /// 手動重載 schemas
+    pub fn reload(&self) -> Result<(), std::io::Error> {
+        let new_registry = self.load_fresh()?;
+        swap_registry(&self.registry, new_registry);
+        println!("🔄 Schemas reloaded");
+        Ok(())
+    }
+
+    /// 原子重載: 整個來源零編譯錯誤且包含 `required` 中所有 schemas 才替換
+    ///
+    /// 任一條件不滿足時完整保留舊 registry 並返回錯誤, 適合部署新 schema 目錄。
+    pub fn reload_atomic(&self, required: &[&str]) -> Result<(), std::io::Error> {
+        let new_registry = self.load_fresh()?;
+
+        let mut problems: Vec<String> = new_registry
+            .load_errors()
+            .iter()
+            .map(|(name, error)| format!("{}: {}", name, error))
+            .collect();
+        problems.sort();
+        for name in required {
+            if !new_registry.schemas.contains_key(*name) {
+                problems.push(format!("required schema '{}' missing", name));
+            }
+        }
+
+        if !problems.is_empty() {
+            eprintln!("⚠️  Reload aborted, keeping previous schemas: {}", problems.join("; "));
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Reload aborted: {}", problems.join("; ")),
+            ));
+        }
+
+        swap_registry(&self.registry, new_registry);
+        println!("🔄 Schemas atomically reloaded");
+        Ok(())
+    }
+
+    /// 驗證數據
+    pub fn validate(&self, schema_name: &str, data: &Value) -> ValidationResult {
+        self.registry.read().unwrap().validate(schema_name, data)
+    }
+
+    /// 目前 registry 的診斷快照 (見 `SchemaRegistry::debug_dump`)
+    pub fn debug_dump(&self) -> Value {
+        self.registry.read().unwrap().debug_dump()
+    }
+
+    /// 同 `debug_dump`, 但每個 schema 附上完整的源
+    pub fn debug_dump_with_sources(&self) -> Value {
+        self.registry.read().unwrap().debug_dump_with_sources()
+    }
+
+    /// 啟動自動監聽文件變化 (需要 notify crate)
+    #[cfg(feature = "watch")]
+    pub fn start_watching(&self) -> notify::Result<()> {
+        use notify::{Watcher, RecursiveMode, watcher};
+        use std::sync::mpsc::channel;
+        use std::time::Duration;
+
+        let (Some(path), Some(source)) = (self.path.clone(), self.source.clone()) else {
+            return Err(notify::Error::Generic("Registry is fed from a channel, not a directory".to_string()));
+        };
+        let builder = Arc::clone(&self.builder);
+
+        let (tx, rx) = channel();
+        let mut watcher = watcher(tx, Duration::from_secs(1))?;
+        watcher.watch(&path, RecursiveMode::Recursive)?;
+
+        let registry = Arc::clone(&self.registry);
+
+        std::thread::spawn(move || {
+            loop {
+                match rx.recv() {
+                    Ok(_event) => {
+                        if let Ok(new_registry) = builder.load_source(source.as_ref()) {
+                            swap_registry(&registry, new_registry);
+                            println!("🔄 Auto-reloaded schemas");
+                        }
+                    }
+                    Err(e) => eprintln!("Watch error: {}", e),
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+/// 以新 registry 原子替換舊的 (目錄重載與 channel 推送共用)
+///
+/// 編譯錯誤歷史會延續到新 registry, 並記錄重載時間。
+fn swap_registry(registry: &RwLock<SchemaRegistry>, mut new_registry: SchemaRegistry) {
+    let mut current = registry.write().unwrap();
+    let mut history = std::mem::take(&mut current.compile_error_history);
+    history.append(&mut new_registry.compile_error_history);
+    while history.len() > MAX_COMPILE_ERROR_HISTORY {
+        history.pop_front();
+    }
+    new_registry.compile_error_history = history;
+    new_registry.last_reload = Some(SystemTime::now());
+    *current = new_registry;
+}
+
+/// 並發驗證達上限時的行為
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// 等待其他驗證完成
+    Queue,
+    /// 立即返回 `Overloaded`
+    Reject,
+}
+
+/// 同步呼叫與 `validate_async` 共用的計數信號量
+///
+/// 啟用 `async` feature 時許可由 `tokio::sync::Semaphore` 計數, 同步排隊的呼叫
+/// 在 `released` 上等待歸還; 否則以 Mutex 計數。
+struct CountingSemaphore {
+    #[cfg(not(feature = "async"))]
+    available: Mutex<usize>,
+    #[cfg(feature = "async")]
+    semaphore: tokio::sync::Semaphore,
+    /// 同步等待者檢查許可與進入等待時持有, 避免錯過歸還通知
+    #[cfg(feature = "async")]
+    waiters: Mutex<()>,
+    released: Condvar,
+}
+
+impl CountingSemaphore {
+    fn new(permits: usize) -> Self {
+        Self {
+            #[cfg(not(feature = "async"))]
+            available: Mutex::new(permits),
+            #[cfg(feature = "async")]
+            semaphore: tokio::sync::Semaphore::new(permits),
+            #[cfg(feature = "async")]
+            waiters: Mutex::new(()),
+            released: Condvar::new(),
+        }
+    }
+
+    /// 等待直到取得許可 (阻塞當前線程)
+    #[cfg(not(feature = "async"))]
+    fn acquire(&self) -> SemaphorePermit<'_> {
+        let mut available = self.available.lock().unwrap();
+        while *available == 0 {
+            available = self.released.wait(available).unwrap();
+        }
+        *available -= 1;
+        SemaphorePermit(self)
+    }
+
+    /// 等待直到取得許可 (阻塞當前線程)
+    #[cfg(feature = "async")]
+    fn acquire(&self) -> SemaphorePermit<'_> {
+        let mut waiters = self.waiters.lock().unwrap();
+        loop {
+            if let Some(permit) = self.try_acquire() {
+                return permit;
+            }
+            waiters = self.released.wait(waiters).unwrap();
+        }
+    }
+
+    /// 等待直到取得許可, 排隊時不阻塞執行緒
+    #[cfg(feature = "async")]
+    async fn acquire_async(&self) -> Option<SemaphorePermit<'_>> {
+        let permit = self.semaphore.acquire().await.ok()?;
+        permit.forget();
+        Some(SemaphorePermit(self))
+    }
+
+    /// 沒有可用許可時返回 None
+    #[cfg(not(feature = "async"))]
+    fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        let mut available = self.available.lock().unwrap();
+        if *available == 0 {
+            return None;
+        }
+        *available -= 1;
+        Some(SemaphorePermit(self))
+    }
+
+    /// 沒有可用許可時返回 None
+    #[cfg(feature = "async")]
+    fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        self.semaphore.try_acquire().ok()?.forget();
+        Some(SemaphorePermit(self))
+    }
+
+    fn available(&self) -> usize {
+        #[cfg(not(feature = "async"))]
+        {
+            *self.available.lock().unwrap()
+        }
+        #[cfg(feature = "async")]
+        {
+            self.semaphore.available_permits()
+        }
+    }
+}
+
+/// drop 時歸還許可
+struct SemaphorePermit<'a>(&'a CountingSemaphore);
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        #[cfg(not(feature = "async"))]
+        {
+            *self.0.available.lock().unwrap() += 1;
+        }
+        #[cfg(feature = "async")]
+        {
+            self.0.semaphore.add_permits(1);
+            drop(self.0.waiters.lock().unwrap());
+        }
+        self.0.released.notify_all();
+    }
+}
+
+/// 限制並發驗證數的 registry wrapper, 避免驗證洪峰耗盡服務的 CPU
+///
+/// 同步呼叫與 `validate_async` (需要 `async` feature) 共用同一組 `limit` 個許可。
+pub struct ConcurrencyLimitedRegistry {
+    registry: Arc<SchemaRegistry>,
+    limit: usize,
+    policy: OverflowPolicy,
+    permits: CountingSemaphore,
+}
+
+impl ConcurrencyLimitedRegistry {
+    /// 創建最多同時執行 `limit` 個驗證的 wrapper (最少為 1)
+    pub fn new(registry: Arc<SchemaRegistry>, limit: usize, policy: OverflowPolicy) -> Self {
+        let limit = limit.max(1);
+        Self {
+            registry,
+            limit,
+            policy,
+            permits: CountingSemaphore::new(limit),
+        }
+    }
+
+    /// 在並發上限內驗證數據, `Reject` 模式下超出上限返回 `Overloaded`
+    pub fn validate(&self, schema_name: &str, data: &Value) -> Result<ValidationResult, Overloaded> {
+        let _permit = match self.policy {
+            OverflowPolicy::Queue => self.permits.acquire(),
+            OverflowPolicy::Reject => self.permits.try_acquire().ok_or(Overloaded { limit: self.limit })?,
+        };
+        Ok(self.registry.validate(schema_name, data))
+    }
+
+    /// `validate` 的 async 版本, 排隊時不阻塞執行緒
+    #[cfg(feature = "async")]
+    pub async fn validate_async(&self, schema_name: &str, data: &Value) -> Result<ValidationResult, Overloaded> {
+        let _permit = match self.policy {
+            OverflowPolicy::Queue => self.permits.acquire_async().await,
+            OverflowPolicy::Reject => self.permits.try_acquire(),
+        }
+        .ok_or(Overloaded { limit: self.limit })?;
+        Ok(self.registry.validate(schema_name, data))
+    }
+
+    /// 目前正在執行的驗證數 (用於監控指標)
+    pub fn in_flight(&self) -> usize {
+        self.limit - self.permits.available()
+    }
+
+    /// 並發驗證上限
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    static TEST_DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    /// 將 schemas 寫入新的臨時目錄
+    /// 測試用的暫存目錄, drop 時連同內容刪除
+    struct TestDir(std::path::PathBuf);
+
+    impl std::ops::Deref for TestDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TestDir {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn schema_dir(schemas: &[(&str, Value)]) -> TestDir {
+        let dir = TestDir(std::env::temp_dir().join(format!(
+            "schema-registry-test-{}-{}",
+            std::process::id(),
+            TEST_DIR_COUNTER.fetch_add(1, Ordering::SeqCst)
+        )));
+        write_schemas(&dir, schemas);
+        dir
+    }
+
+    fn write_schemas(dir: &Path, schemas: &[(&str, Value)]) {
+        fs::create_dir_all(dir).unwrap();
+        for (name, schema) in schemas {
+            fs::write(dir.join(format!("{}.json", name)), schema.to_string()).unwrap();
+        }
+    }
+
+    /// 建立含多個版本子目錄的臨時目錄
+    fn versioned_dir(versions: &[(&str, &[(&str, Value)])]) -> TestDir {
+        let base = schema_dir(&[]);
+        for (version, schemas) in versions {
+            write_schemas(&base.join(version), schemas);
+        }
+        base
+    }
+
+    /// 將 schemas 寫入臨時目錄後載入
+    fn registry_with(schemas: &[(&str, Value)]) -> SchemaRegistry {
+        let dir = schema_dir(schemas);
+        SchemaRegistry::from_directory(&dir).expect("Failed to load schemas")
+    }
+
+    #[test]
+    fn test_schema_registry() {
+        let registry = SchemaRegistry::from_directory("./schemas/v1")
+            .expect("Failed to load schemas");
+
+        // Valid data
+        let valid_user = json!({
+            "email": "alice@example.com",
+            "name": "Alice"
+        });
+        assert!(registry.validate("user", &valid_user).is_ok());
+
+        // Invalid data
+        let invalid_user = json!({
+            "name": "Bob"  // Missing email
+        });
+        assert!(registry.validate("user", &invalid_user).is_err());
+    }
+
+    #[test]
+    fn test_versioned_registry() {
+        let registry = VersionedRegistry::new("./schemas", &["v1", "v2"])
+            .expect("Failed to load versions");
+
+        let data = json!({"email": "test@example.com", "name": "Test"});
+
+        // Test v1
+        assert!(registry.validate(Some("v1"), "user", &data).is_ok());
+
+        // Test v2
+        assert!(registry.validate(Some("v2"), "user", &data).is_ok());
+
+        // Test default version (v2)
+        assert!(registry.validate(None, "user", &data).is_ok());
+    }
+
+    #[test]
+    fn test_first_missing_required() {
+        let registry = registry_with(&[(
+            "signup",
+            json!({
+                "type": "object",
+                "properties": {
+                    "address": {
+                        "type": "object",
+                        "properties": {"city": {"type": "string"}},
+                        "required": ["city"]
+                    },
+                    "email": {"type": "string"}
+                },
+                "required": ["email"]
+            }),
+        )]);
+
+        let data = json!({"address": {}});
+        assert_eq!(
+            registry.first_missing_required("signup", &data),
+            Some("/email".to_string())
+        );
+
+        let data = json!({"address": {}, "email": "a@b.c"});
+        assert_eq!(
+            registry.first_missing_required("signup", &data),
+            Some("/address/city".to_string())
+        );
+
+        let data = json!({"address": {"city": "Taipei"}});
+        assert_eq!(
+            registry.first_missing_required("signup", &data),
+            Some("/email".to_string())
+        );
+
+        let data = json!({"address": {"city": "Taipei"}, "email": "a@b.c"});
+        assert_eq!(registry.first_missing_required("signup", &data), None);
+
+        let registry = registry_with(&[(
+            "links",
+            json!({
+                "properties": {"a/b": {"required": ["x~y"]}},
+                "required": ["a/b", "c~d"]
+            }),
+        )]);
+        assert_eq!(registry.first_missing_required("links", &json!({"a/b": {}})), Some("/a~1b/x~0y".to_string()));
+        assert_eq!(registry.first_missing_required("links", &json!({"a/b": {"x~y": 1}})), Some("/c~0d".to_string()));
+
+        // 依 required 的文件順序, 而非 properties 的鍵排序
+        let registry = registry_with(&[(
+            "shipping",
+            json!({
+                "properties": {"zip": {"type": "string"}, "age": {"type": "integer"}},
+                "required": ["zip", "age"]
+            }),
+        )]);
+        assert_eq!(registry.first_missing_required("shipping", &json!({})), Some("/zip".to_string()));
+        assert_eq!(registry.first_missing_required("shipping", &json!({"zip": "10001"})), Some("/age".to_string()));
+    }
+
+    #[test]
+    fn test_validate_coerced_with_locale() {
+        let registry = registry_with(&[(
+            "reading",
+            json!({
+                "type": "object",
+                "properties": {
+                    "value": {"type": "number"},
+                    "count": {"type": "integer"},
+                    "date": {"type": "string", "format": "date"}
+                },
+                "required": ["value", "date"]
+            }),
+        )]);
+        let opts = CoerceOptions { locale: Locale::european() };
+
+        let data = json!({"value": "1.234,5", "count": "3", "date": "31/12/2024"});
+        let coerced = registry.validate_coerced("reading", &data, &opts).unwrap();
+        assert_eq!(coerced, json!({"value": 1234.5, "count": 3, "date": "2024-12-31"}));
+
+        // 只有 coercion 模式理解地區格式
+        assert!(registry.validate("reading", &data).is_err());
+
+        // 千分位符號位置不符時不轉換
+        for value in ["1.5", "1.2.3", "12.34,5"] {
+            let data = json!({"value": value, "date": "31/12/2024"});
+            assert!(registry.validate_coerced("reading", &data, &opts).is_err(), "{}", value);
+        }
+        let data = json!({"value": "-12.345.678", "date": "31/12/2024"});
+        assert_eq!(registry.validate_coerced("reading", &data, &opts).unwrap()["value"], json!(-12345678.0));
+
+        // 超出 i64 範圍或非有限的數字保留原字串
+        for count in ["1e30", "-1e30", "inf", "NaN"] {
+            let data = json!({"value": "1", "count": count, "date": "31/12/2024"});
+            assert!(registry.validate_coerced("reading", &data, &opts).is_err(), "{}", count);
+        }
+        let data = json!({"value": "1e30", "date": "31/12/2024"});
+        assert_eq!(registry.validate_coerced("reading", &data, &opts).unwrap()["value"], json!(1e30));
+    }
+
+    #[test]
+    fn test_variant_fields() {
+        let registry = registry_with(&[
+            (
+                "payment",
+                json!({
+                    "oneOf": [
+                        {"properties": {"kind": {"const": "card"}, "card_number": {"type": "string"}}},
+                        {"properties": {"iban": {"type": "string"}, "kind": {"const": "bank"}}}
+                    ]
+                }),
+            ),
+            ("plain", json!({"properties": {"name": {"type": "string"}}})),
+        ]);
+
+        assert_eq!(
+            registry.variant_fields("payment"),
+            vec![vec!["card_number", "kind"], vec!["iban", "kind"]]
+        );
+        assert_eq!(registry.variant_fields("plain"), vec![vec!["name"]]);
+    }
+
+    #[test]
+    fn test_reload_atomic_keeps_old_registry_on_failure() {
+        let dir = schema_dir(&[("user", json!({"required": ["email"]}))]);
+        let hot = HotReloadRegistry::new(dir.to_str().unwrap()).unwrap();
+
+        fs::write(dir.join("broken.json"), json!({"type": 12}).to_string()).unwrap();
+        assert!(hot.reload_atomic(&[]).is_err());
+        assert!(hot.validate("user", &json!({})).is_err());
+
+        fs::remove_file(dir.join("broken.json")).unwrap();
+        assert!(hot.reload_atomic(&["user", "order"]).is_err());
+        assert!(hot.reload_atomic(&["user"]).is_ok());
+    }
+
+    #[test]
+    fn test_validate_normalize() {
+        let registry = registry_with(&[(
+            "profile",
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string"},
+                    "role": {"type": "string", "default": "member"}
+                },
+                "required": ["name"]
+            }),
+        )]);
+
+        let data = json!({"name": "Alice", "debug": true});
+        let normalized = registry
+            .validate_normalize("profile", &data, NormalizeOptions::default())
+            .unwrap();
+        assert_eq!(normalized, json!({"name": "Alice", "role": "member"}));
+
+        let opts = NormalizeOptions { apply_defaults: false, strip_unknown: false, ..Default::default() };
+        let normalized = registry.validate_normalize("profile", &data, opts).unwrap();
+        assert_eq!(normalized, data);
+    }
+
+    #[test]
+    fn test_try_validate_error() {
+        let registry = registry_with(&[(
+            "user",
+            json!({"required": ["email", "name"], "properties": {"age": {"type": "integer"}}}),
+        )]);
+
+        let err = registry.try_validate("user", &json!({"age": "x"})).unwrap_err();
+        assert_eq!(err.errors.len(), 3);
+        assert!(err.errors.iter().any(|e| e.keyword == "type" && e.instance_path == "/age"));
+        assert!(err.to_string().starts_with("3 validation error(s) for schema 'user': "));
+
+        let boxed: Box<dyn std::error::Error> = Box::new(err);
+        assert!(!boxed.to_string().is_empty());
+        assert!(registry.try_validate("user", &json!({"email": "a", "name": "b"})).is_ok());
+    }
+
+    #[test]
+    fn test_hot_reload_from_channel() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let hot = HotReloadRegistry::from_channel(rx);
+        assert!(hot.validate("user", &json!({})).is_err());
+        assert!(hot.reload().is_err());
+
+        let dir = schema_dir(&[("user", json!({"type": "object"}))]);
+        tx.send(SchemaRegistry::from_directory(&dir).unwrap()).unwrap();
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while hot.validate("user", &json!({})).is_err() {
+            assert!(std::time::Instant::now() < deadline, "update never arrived");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_validate_with_format_overrides() {
+        let registry = registry_with(&[(
+            "contact",
+            json!({"properties": {"email": {"type": "string", "format": "email"}}}),
+        )]);
+        let data = json!({"email": "alice@gmail.com"});
+
+        let mut overrides: HashMap<String, FormatValidator> = HashMap::new();
+        overrides.insert("email".to_string(), Arc::new(|s: &str| s.ends_with("@corp.com")));
+
+        assert!(registry.validate("contact", &data).is_ok());
+        assert!(registry.validate_with_format_overrides("contact", overrides, &data).is_err());
+    }
+
+    #[test]
+    fn test_accumulated_coverage() {
+        let mut registry = registry_with(&[(
+            "order",
+            json!({
+                "properties": {
+                    "id": {"type": "string"},
+                    "status": {"enum": ["open", "closed"]},
+                    "note": {"type": "string"}
+                }
+            }),
+        )]);
+        assert!(registry.accumulated_coverage("order").is_none());
+
+        registry.enable_coverage_tracking();
+        registry.validate("order", &json!({"id": "1", "status": "open"})).unwrap();
+        registry.validate("order", &json!({"id": "2"})).unwrap();
+
+        let report = registry.accumulated_coverage("order").unwrap();
+        assert_eq!(report.unseen_properties, BTreeSet::from(["note".to_string()]));
+        assert_eq!(
+            report.unseen_enum_values["status"],
+            BTreeSet::from(["\"closed\"".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_validate_with_schema_hash() {
+        let registry = registry_with(&[("user", json!({"required": ["email"]}))]);
+        let hash = registry.schema_hash("user").unwrap();
+        assert_eq!(hash.len(), 16);
+
+        let opts = ValidateOptions { include_schema_hash: true, ..Default::default() };
+        let errors = registry.validate_with_options("user", &json!({}), &opts).unwrap_err();
+        assert_eq!(errors[0].schema_hash.as_deref(), Some(hash.as_str()));
+
+        let errors = registry
+            .validate_with_options("user", &json!({}), &ValidateOptions::default())
+            .unwrap_err();
+        assert_eq!(errors[0].schema_hash, None);
+    }
+
+    #[cfg(all(feature = "yaml", feature = "json5"))]
+    #[test]
+    fn test_format_precedence() {
+        let dir = schema_dir(&[("user", json!({"required": ["json"]}))]);
+        fs::write(dir.join("user.json5"), "{required: ['json5']}").unwrap();
+        fs::write(dir.join("user.yaml"), "required: [yaml]").unwrap();
+
+        let registry = SchemaRegistry::from_directory(&dir).unwrap();
+        assert_eq!(registry.get_schema_source("user"), Some(&json!({"required": ["json"]})));
+
+        let registry = SchemaRegistry::builder()
+            .with_format_precedence(&[Format::Yaml, Format::Json])
+            .load(&dir)
+            .unwrap();
+        assert_eq!(registry.get_schema_source("user"), Some(&json!({"required": ["yaml"]})));
+    }
+
+    #[test]
+    fn test_detect_draft() {
+        assert_eq!(
+            detect_draft(&json!({"$schema": "http://json-schema.org/draft-07/schema#"})),
+            Some(Draft::Draft7)
+        );
+        assert_eq!(
+            detect_draft(&json!({"$schema": "https://json-schema.org/draft/2020-12/schema"})),
+            Some(Draft::Draft202012)
+        );
+        assert_eq!(detect_draft(&json!({"type": "object"})), Some(Draft::Draft202012));
+
+        let dir = schema_dir(&[("legacy", json!({"$schema": "http://json-schema.org/draft-04/schema#"}))]);
+        let registry = SchemaRegistry::builder().auto_draft(true).load(&dir).unwrap();
+        assert_eq!(registry.draft("legacy"), Some(Draft::Draft4));
+    }
+
+    #[test]
+    fn test_validate_with_stats() {
+        let registry = registry_with(&[("user", json!({"required": ["email", "name"]}))]);
+
+        let (result, stats) = registry.validate_with_stats("user", &json!({"tags": ["a", "b"]}));
+        assert!(result.is_err());
+        assert_eq!(stats.nodes_visited, 4);
+        assert_eq!(stats.errors_found, 2);
+    }
+
+    #[test]
+    fn test_find_by_property() {
+        let registry = registry_with(&[
+            ("user", json!({"properties": {"email": {"type": "string"}}})),
+            (
+                "order",
+                json!({"properties": {"buyer": {"properties": {"email": {"type": "string"}}}}}),
+            ),
+            ("product", json!({"properties": {"sku": {"type": "string"}}})),
+        ]);
+
+        assert_eq!(registry.find_by_property("email"), vec!["order", "user"]);
+        assert!(registry.find_by_property("phone").is_empty());
+    }
+
+    #[test]
+    fn test_source_transforms_run_in_order() {
+        let dir = schema_dir(&[("user", json!({"properties": {"name": {"type": "string"}}}))]);
+        let registry = SchemaRegistry::builder()
+            .with_source_transform(|name, source| {
+                source["$id"] = json!(format!("https://acme.com/schemas/{}", name));
+            })
+            .with_source_transform(|_, source| {
+                if source.get("additionalProperties").is_none() {
+                    source["additionalProperties"] = json!(false);
+                }
+            })
+            .load(&dir)
+            .unwrap();
+
+        let source = registry.get_schema_source("user").unwrap();
+        assert_eq!(source["$id"], "https://acme.com/schemas/user");
+        assert!(registry.validate("user", &json!({"name": "a", "extra": 1})).is_err());
+    }
+
+    #[test]
+    fn test_validate_batch_with_deadline() {
+        let registry = registry_with(&[("user", json!({"required": ["email"]}))]);
+        let items = vec![json!({"email": "a@b.c"}), json!({})];
+
+        let results = registry.validate_batch_with_deadline("user", &items, Duration::from_secs(5));
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0], Ok(Ok(()))));
+        assert!(matches!(results[1], Ok(Err(_))));
+    }
+
+    #[test]
+    fn test_deadline_caps_abandoned_workers() {
+        // `gated` format 在閘門打開前阻塞, 讓工作線程確定超時
+        let gate = Arc::new((Mutex::new(false), Condvar::new()));
+        let worker_gate = Arc::clone(&gate);
+        let schema = json!({"properties": {"code": {"format": "gated"}}});
+        let compiled = JSONSchema::options()
+            .should_validate_formats(true)
+            .with_format("gated", move |_: &str| {
+                let (open, opened) = &*worker_gate;
+                let mut open = open.lock().unwrap();
+                while !*open {
+                    open = opened.wait(open).unwrap();
+                }
+                true
+            })
+            .compile(&schema)
+            .unwrap();
+        let mut registry = registry_with(&[("code", schema)]);
+        registry.schemas.insert("code".to_string(), Arc::new(compiled));
+
+        let items = vec![json!({"code": "x"}); MAX_ABANDONED_WORKERS + 2];
+        let results = registry.validate_batch_with_deadline("code", &items, Duration::from_millis(5));
+        let (timed_out, overloaded) = results.split_at(MAX_ABANDONED_WORKERS);
+        assert!(timed_out.iter().all(|result| matches!(result, Err(DeadlineError::TimedOut(_)))));
+        let expected = Err(DeadlineError::Overloaded(Overloaded { limit: MAX_ABANDONED_WORKERS }));
+        assert!(overloaded.iter().all(|result| *result == expected));
+
+        // 背景線程跑完後恢復驗證
+        let (open, opened) = &*gate;
+        *open.lock().unwrap() = true;
+        opened.notify_all();
+        let started = Instant::now();
+        let result = loop {
+            match registry.validate_with_deadline("code", &json!({"code": "x"}), Duration::from_secs(5)) {
+                Err(DeadlineError::Overloaded(_)) if started.elapsed() < Duration::from_secs(5) => {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                result => break result,
+            }
+        };
+        assert_eq!(result, Ok(Ok(())));
+    }
+
+    #[test]
+    fn test_unused_defs() {
+        let registry = registry_with(&[(
+            "order",
+            json!({
+                "properties": {
+                    "shipping": {"$ref": "#/$defs/address"},
+                    "billing": {"$ref": "#/$defs/adress"}
+                },
+                "$defs": {
+                    "address": {"type": "object"},
+                    "adress": {"type": "object"},
+                    "money": {"type": "number"}
+                }
+            }),
+        )]);
+
+        assert_eq!(registry.unused_defs("order"), vec!["/$defs/money"]);
+    }
+
+    #[test]
+    fn test_extension_router() {
+        let registry = registry_with(&[
+            ("invoice", json!({"required": ["total"]})),
+            ("generic", json!({"type": "object"})),
+        ]);
+
+        let router = ExtensionRouter::new(&registry).route(".INV", "invoice");
+        assert_eq!(router.schema_for("march.inv"), Some("invoice"));
+        assert!(router.validate_file("march.inv", &json!({})).is_err());
+        assert!(router.validate_file("photo.png", &json!({})).is_err());
+
+        let router = router.with_default("generic");
+        assert!(router.validate_file("photo.png", &json!({})).is_ok());
+    }
+
+    #[test]
+    fn test_max_error_bytes_truncates() {
+        let registry = registry_with(&[("user", json!({"required": ["a", "b", "c", "d", "e"]}))]);
+
+        let opts = ValidateOptions { max_error_bytes: Some(250), ..Default::default() };
+        let errors = registry.validate_with_options("user", &json!({}), &opts).unwrap_err();
+        assert!(errors.len() < 5);
+        assert_eq!(errors.last().unwrap().keyword, "truncated");
+
+        let errors = registry
+            .validate_with_options("user", &json!({}), &ValidateOptions::default())
+            .unwrap_err();
+        assert_eq!(errors.len(), 5);
+    }
+
+    #[test]
+    fn test_sanitizer_pipeline() {
+        let registry = registry_with(&[(
+            "signup",
+            json!({
+                "type": "object",
+                "properties": {
+                    "age": {"type": "integer"},
+                    "plan": {"type": "string", "default": "free"}
+                },
+                "additionalProperties": false
+            }),
+        )]);
+        let data = json!({"age": "42", "csrf": "x"});
+
+        let sanitized = Sanitizer::new(SanitizeOptions::default())
+            .process(&registry, "signup", &data)
+            .unwrap();
+        assert_eq!(sanitized, json!({"age": 42, "plan": "free"}));
+
+        let opts = SanitizeOptions { steps: vec![SanitizeStep::Coerce], ..Default::default() };
+        assert!(Sanitizer::new(opts).process(&registry, "signup", &data).is_err());
+    }
+
+    #[test]
+    fn test_register_derived() {
+        let mut registry = registry_with(&[(
+            "user",
+            json!({"properties": {"address": {"type": "string"}}, "required": ["email"]}),
+        )]);
+
+        registry
+            .register_derived("user_with_address", "user", |source| {
+                source["required"].as_array_mut().unwrap().push(json!("address"));
+            })
+            .unwrap();
+
+        let data = json!({"email": "a@b.c"});
+        assert!(registry.validate("user", &data).is_ok());
+        assert!(registry.validate("user_with_address", &data).is_err());
+        assert_eq!(registry.derived_base("user_with_address"), Some("user"));
+        assert_eq!(registry.derived_schemas("user"), vec!["user_with_address"]);
+        assert!(registry.register_derived("x", "missing", |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_max_string_bytes_guard() {
+        let registry = registry_with(&[("doc", json!({"type": "object"}))]);
+        let data = json!({"tags": ["ok", "x".repeat(100)]});
+
+        let opts = ValidateOptions { max_string_bytes: Some(64), ..Default::default() };
+        let errors = registry.validate_with_options("doc", &data, &opts).unwrap_err();
+        assert_eq!(errors[0].instance_path, "/tags/1");
+        assert!(registry.validate_with_options("doc", &data, &ValidateOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_observable_events() {
+        let registry = registry_with(&[("user", json!({"required": ["email", "name"]}))]);
+
+        let events: Vec<ValidationEvent> = registry.validate_observable("user", &json!({})).iter().collect();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0], ValidationEvent::Started { schema_name: "user".to_string() });
+        assert!(matches!(events[1], ValidationEvent::Error(_)));
+        assert_eq!(events[3], ValidationEvent::Finished { error_count: 2 });
+    }
+
+    #[test]
+    fn test_numeric_epsilon_relaxes_bounds() {
+        let registry = registry_with(&[(
+            "sensor",
+            json!({"properties": {"level": {"type": "number", "minimum": 0, "maximum": 1}}}),
+        )]);
+        let noisy = json!({"level": -0.0000001});
+
+        assert!(registry.validate("sensor", &noisy).is_err());
+        let opts = ValidateOptions { numeric_epsilon: Some(1e-6), ..Default::default() };
+        assert!(registry.validate_with_options("sensor", &noisy, &opts).is_ok());
+        assert!(registry.validate_with_options("sensor", &json!({"level": -0.1}), &opts).is_err());
+    }
+
+    #[test]
+    fn test_data_dictionary_csv() {
+        let registry = registry_with(&[(
+            "user",
+            json!({
+                "properties": {
+                    "email": {"type": "string", "description": "Login, unique", "x-sensitive": "pii"},
+                    "address": {"type": "object", "properties": {"city": {"type": "string"}}}
+                },
+                "required": ["email"]
+            }),
+        )]);
+
+        assert_eq!(
+            registry.to_data_dictionary_csv(),
+            "schema,field_path,type,required,description,sensitivity\n\
+             user,address,object,false,,\n\
+             user,address.city,string,false,,\n\
+             user,email,string,true,\"Login, unique\",pii\n"
+        );
+    }
+
+    #[test]
+    fn test_present_optional_fields() {
+        let registry = registry_with(&[(
+            "user",
+            json!({
+                "properties": {
+                    "email": {"type": "string"},
+                    "nickname": {"type": "string"},
+                    "address": {
+                        "properties": {"city": {"type": "string"}, "zip": {"type": "string"}},
+                        "required": ["city"]
+                    }
+                },
+                "required": ["email", "address"]
+            }),
+        )]);
+
+        let data = json!({"email": "a@b.c", "address": {"city": "Taipei", "zip": "100"}, "extra": 1});
+        assert_eq!(registry.present_optional_fields("user", &data), vec!["address.zip"]);
+    }
+
+    #[test]
+    fn test_treat_null_as_missing() {
+        let registry = registry_with(&[(
+            "user",
+            json!({
+                "properties": {"email": {"type": "string"}, "nickname": {"type": "string"}},
+                "required": ["email"]
+            }),
+        )]);
+        let opts = ValidateOptions { treat_null_as_missing: true, ..Default::default() };
+
+        let data = json!({"email": "a@b.c", "nickname": null});
+        assert!(registry.validate("user", &data).is_err());
+        assert!(registry.validate_with_options("user", &data, &opts).is_ok());
+
+        let errors = registry
+            .validate_with_options("user", &json!({"email": null}), &opts)
+            .unwrap_err();
+        assert_eq!(errors[0].keyword, "required");
+    }
+
+    #[test]
+    fn test_validate_data_directory() {
+        let registry = registry_with(&[("user", json!({"required": ["email"]}))]);
+        let data_dir = schema_dir(&[
+            ("user-1", json!({"email": "a@b.c"})),
+            ("user-2", json!({})),
+            ("misc", json!({})),
+        ]);
+        fs::write(data_dir.join("user-3.json"), [0xff, 0xfe, 0x00]).unwrap();
+
+        let report = registry
+            .validate_data_directory(&data_dir, |path| {
+                let stem = path.file_stem()?.to_str()?;
+                stem.starts_with("user-").then(|| "user".to_string())
+            })
+            .unwrap();
+
+        assert!(report.results[&data_dir.join("user-1.json")].is_ok());
+        assert!(report.results[&data_dir.join("user-2.json")].is_err());
+        let unreadable = report.results[&data_dir.join("user-3.json")].as_ref().unwrap_err();
+        assert!(unreadable[0].starts_with("Failed to read"));
+        assert_eq!(report.unmatched, vec![data_dir.join("misc.json")]);
+    }
+
+    #[test]
+    fn test_cross_field_rules() {
+        let registry = registry_with(&[(
+            "booking",
+            json!({
+                "properties": {"start": {"type": "integer"}, "end": {"type": "integer"}},
+                "required": ["start", "end"]
+            }),
+        )])
+        .with_cross_field_rule("booking", |data| {
+            if data["end"].as_i64() > data["start"].as_i64() {
+                Ok(())
+            } else {
+                Err("end must be after start".to_string())
+            }
+        });
+
+        assert!(registry.validate("booking", &json!({"start": 1, "end": 2})).is_ok());
+        assert_eq!(
+            registry.validate("booking", &json!({"start": 2, "end": 1})),
+            Err(vec!["end must be after start".to_string()])
+        );
+        // 結構錯誤時不執行規則
+        assert_eq!(registry.validate("booking", &json!({"start": 2})).unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn test_sort_errors() {
+        let registry = registry_with(&[(
+            "user",
+            json!({"properties": {"age": {"type": "integer"}}, "required": ["name", "email"]}),
+        )]);
+        let opts = ValidateOptions { sort_errors: true, ..Default::default() };
+
+        let errors = registry
+            .validate_with_options("user", &json!({"age": "x"}), &opts)
+            .unwrap_err();
+        let keys: Vec<(&str, &str)> = errors
+            .iter()
+            .map(|e| (e.instance_path.as_str(), e.keyword.as_str()))
+            .collect();
+        assert_eq!(keys, vec![("", "required"), ("", "required"), ("/age", "type")]);
+        assert!(errors[0].message < errors[1].message);
+    }
+
+    #[test]
+    fn test_permissive_unknown_schema() {
+        let dir = schema_dir(&[("user", json!({"required": ["email"]}))]);
+
+        let strict = SchemaRegistry::from_directory(&dir).unwrap();
+        assert!(strict.validate("event", &json!({})).is_err());
+
+        let permissive = SchemaRegistry::builder().permissive_unknown_schema(true).load(&dir).unwrap();
+        assert!(permissive.validate("event", &json!({})).is_ok());
+        assert!(permissive.is_valid("event", &json!({})));
+        assert!(permissive.validate("user", &json!({})).is_err());
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(permissive.validate_with_deadline("event", &json!({}), timeout), Ok(Ok(())));
+        assert_eq!(permissive.validate_batch_with_deadline("event", &[json!({}), json!([])], timeout), vec![
+            Ok(Ok(())),
+            Ok(Ok(()))
+        ]);
+        let events: Vec<ValidationEvent> = permissive.validate_observable("event", &json!({})).iter().collect();
+        assert!(matches!(events.last(), Some(ValidationEvent::Finished { error_count: 0 })));
+        assert!(strict.validate_with_deadline("event", &json!({}), timeout).unwrap().is_err());
+
+        assert!(permissive.validate_with_format_overrides("event", HashMap::new(), &json!({})).is_ok());
+        assert!(permissive.validate_with_extra_required("event", &["id"], &json!({})).is_ok());
+        assert!(permissive.validate_with_enum_override("event", "kind", &[json!("a")], &json!({})).is_ok());
+        assert!(permissive.validate_closed("event", &json!({"extra": 1}), None).is_ok());
+        assert!(strict.validate_closed("event", &json!({}), None).is_err());
+        assert!(strict.validate_with_extra_required("event", &["id"], &json!({})).is_err());
+        #[cfg(feature = "rayon")]
+        {
+            assert!(permissive.validate_fields_par("event", &json!({"id": 1})).is_empty());
+            assert!(strict.validate_fields_par("event", &json!({"id": 1}))[""].is_err());
+        }
+    }
+
+    #[test]
+    fn test_compatibility_levels() {
+        let base = versioned_dir(&[
+            ("v1", &[("user", json!({"properties": {"age": {"type": "integer"}}, "required": ["email"]}))]),
+            ("v2", &[("user", json!({"properties": {"age": {"type": "integer"}}, "required": ["email", "name"]}))]),
+            ("v3", &[("user", json!({"properties": {"age": {"type": "string"}}, "required": ["email"]}))]),
+        ]);
+        let registry = VersionedRegistry::new(base.to_str().unwrap(), &["v1", "v2", "v3"]).unwrap();
+
+        assert_eq!(registry.compatibility("v1", "v1", "user"), Compatibility::Full);
+        assert_eq!(registry.compatibility("v1", "v2", "user"), Compatibility::Forward);
+        assert_eq!(registry.compatibility("v2", "v1", "user"), Compatibility::Backward);
+        assert_eq!(registry.compatibility("v1", "v3", "user"), Compatibility::Breaking);
+        assert_eq!(registry.compatibility("v1", "v9", "user"), Compatibility::Breaking);
+    }
+
+    #[test]
+    fn test_validate_closed_depth() {
+        let registry = registry_with(&[(
+            "doc",
+            json!({
+                "properties": {
+                    "meta": {"properties": {"author": {"type": "string"}}}
+                }
+            }),
+        )]);
+        let data = json!({"extra": 1, "meta": {"author": "a", "draft": true}});
+
+        assert_eq!(
+            registry.validate_closed("doc", &data, None),
+            Err(vec!["Unknown property at /extra".to_string(), "Unknown property at /meta/draft".to_string()])
+        );
+        assert_eq!(
+            registry.validate_closed("doc", &data, Some(1)),
+            Err(vec!["Unknown property at /extra".to_string()])
+        );
+        assert_eq!(
+            registry.validate_and_strip_to_depth("doc", &data, Some(1)).unwrap(),
+            json!({"meta": {"author": "a", "draft": true}})
+        );
+    }
+
+    #[test]
+    fn test_max_ref_depth() {
+        let dir = schema_dir(&[
+            (
+                "chained",
+                json!({
+                    "$ref": "#/$defs/a",
+                    "$defs": {
+                        "a": {"$ref": "#/$defs/b"},
+                        "b": {"$ref": "#/$defs/c"},
+                        "c": {"type": "string"}
+                    }
+                }),
+            ),
+            ("flat", json!({"properties": {"x": {"$ref": "#/$defs/x"}}, "$defs": {"x": {"type": "string"}}})),
+        ]);
+
+        let registry = SchemaRegistry::builder().max_ref_depth(2).load(&dir).unwrap();
+        assert!(registry.validate("flat", &json!({"x": "ok"})).is_ok());
+        assert_eq!(
+            registry.load_errors()["chained"],
+            "Reference chain exceeds max_ref_depth 2: #/$defs/a -> #/$defs/b -> #/$defs/c"
+        );
+
+        let registry = SchemaRegistry::builder().max_ref_depth(3).load(&dir).unwrap();
+        assert!(registry.validate("chained", &json!("ok")).is_ok());
+
+        let mut registry = SchemaRegistry::builder().max_ref_depth(2).load(&dir).unwrap();
+        let chained = json!({
+            "$ref": "#/$defs/a",
+            "$defs": {"a": {"$ref": "#/$defs/b"}, "b": {"$ref": "#/$defs/c"}, "c": {"type": "string"}}
+        });
+        assert_eq!(
+            registry.register_schema("runtime", chained.clone()),
+            Err("Failed to compile schema runtime: Reference chain exceeds max_ref_depth 2: \
+                 #/$defs/a -> #/$defs/b -> #/$defs/c".to_string())
+        );
+        assert!(registry.register_derived("derived", "flat", |source| *source = chained.clone()).is_err());
+        assert!(registry.validate("runtime", &json!("ok")).is_err());
+    }
+
+    #[test]
+    fn test_constants() {
+        let registry = registry_with(&[(
+            "event",
+            json!({
+                "properties": {
+                    "kind": {"const": "signup"},
+                    "source": {"enum": ["web"]},
+                    "meta": {"properties": {"schema_version": {"const": 2}}}
+                }
+            }),
+        )]);
+
+        let constants = registry.constants("event");
+        assert_eq!(constants.len(), 3);
+        assert_eq!(constants["meta.schema_version"], json!(2));
+
+        let opts = NormalizeOptions { fill_constants: true, ..Default::default() };
+        let normalized = registry.validate_normalize("event", &json!({"meta": {}}), opts).unwrap();
+        assert_eq!(normalized, json!({"kind": "signup", "source": "web", "meta": {"schema_version": 2}}));
+    }
+
+    /// 測試用的記憶體來源, 內容可在重載間替換
+    struct MemorySource(Arc<Mutex<Vec<(String, Value)>>>);
+
+    impl SchemaSource for MemorySource {
+        fn load(&self) -> Result<Vec<(String, Value)>, SourceError> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+    }
+
+    struct FailingSource;
+
+    impl SchemaSource for FailingSource {
+        fn load(&self) -> Result<Vec<(String, Value)>, SourceError> {
+            Err(SourceError::Other("connection refused".into()))
+        }
+    }
+
+    #[test]
+    fn test_from_source() {
+        let schemas = Arc::new(Mutex::new(vec![(
+            "user".to_string(),
+            json!({"type": "object", "required": ["id"]}),
+        )]));
+        let registry = SchemaRegistry::from_source(&MemorySource(Arc::clone(&schemas))).unwrap();
+        assert!(registry.validate("user", &json!({"id": 1})).is_ok());
+        assert!(registry.validate("user", &json!({})).is_err());
+
+        let err = SchemaRegistry::from_source(&FailingSource).err().unwrap();
+        assert_eq!(err.to_string(), "connection refused");
+
+        let hot = HotReloadRegistry::from_source(MemorySource(Arc::clone(&schemas))).unwrap();
+        assert!(hot.validate("order", &json!({})).is_err());
+        schemas.lock().unwrap().push(("order".to_string(), json!({"type": "object"})));
+        hot.reload().unwrap();
+        assert!(hot.validate("order", &json!({})).is_ok());
+    }
+
+    #[test]
+    fn test_validate_with_fallback() {
+        let registry = registry_with(&[
+            ("order_v2", json!({"type": "object", "required": ["id", "currency"]})),
+            ("order_legacy", json!({"type": "object", "required": ["id"]})),
+        ]);
+
+        let result = registry.validate_with_fallback("order_v2", "order_legacy", &json!({"id": 1, "currency": "EUR"}));
+        assert_eq!(result, FallbackResult::Primary);
+
+        let result = registry.validate_with_fallback("order_v2", "order_legacy", &json!({"id": 1}));
+        assert!(matches!(&result, FallbackResult::Fallback { primary_errors } if primary_errors.len() == 1));
+        assert!(result.is_accepted());
+
+        let result = registry.validate_with_fallback("order_v2", "order_legacy", &json!({}));
+        assert!(matches!(&result, FallbackResult::Rejected { fallback_errors, .. } if fallback_errors.len() == 1));
+        assert!(!result.is_accepted());
+    }
+
+    #[test]
+    fn test_with_base_uri() {
+        let dir = schema_dir(&[
+            ("address", json!({"type": "object", "required": ["city"]})),
+            ("user", json!({
+                "type": "object",
+                "properties": {"address": {"$ref": "https://acme.com/schemas/address"}}
+            })),
+        ]);
+        let registry = SchemaRegistry::builder()
+            .with_base_uri("https://acme.com/schemas/")
+            .load(&dir)
+            .unwrap();
+
+        assert_eq!(registry.get_schema_source("address").unwrap()["$id"], "https://acme.com/schemas/address");
+        assert!(registry.validate("user", &json!({"address": {"city": "Taipei"}})).is_ok());
+        assert!(registry.validate("user", &json!({"address": {}})).is_err());
+
+        // 變體與臨時編譯沿用同一組 `$id` 文件, 不會嘗試從網路取得
+        assert!(registry
+            .validate_with_extra_required("user", &["address"], &json!({"address": {"city": "Taipei"}}))
+            .is_ok());
+        assert!(registry.validate_with_extra_required("user", &["address"], &json!({})).is_err());
+        let opts = ValidateOptions { numeric_epsilon: Some(0.01), ..Default::default() };
+        assert!(registry.validate_with_options("user", &json!({"address": {}}), &opts).is_err());
+        assert!(registry
+            .validate_with_format_overrides("user", HashMap::new(), &json!({"address": {"city": "Taipei"}}))
+            .is_ok());
+
+        let mut registry = registry;
+        registry.register_schema("order", json!({
+            "properties": {"ship_to": {"$ref": "https://acme.com/schemas/address"}}
+        })).unwrap();
+        assert_eq!(registry.get_schema_source("order").unwrap()["$id"], "https://acme.com/schemas/order");
+        assert!(registry.validate("order", &json!({"ship_to": {}})).is_err());
+        assert!(registry.validate("order", &json!({"ship_to": {"city": "Tainan"}})).is_ok());
+    }
+
+    #[test]
+    fn test_error_histogram() {
+        let registry = registry_with(&[(
+            "user",
+            json!({
+                "type": "object",
+                "properties": {"age": {"type": "integer"}},
+                "required": ["id", "email"]
+            }),
+        )]);
+
+        let histogram = registry.error_histogram("user", &json!({"age": "old"}));
+        assert_eq!(histogram, HashMap::from([("required".to_string(), 2), ("type".to_string(), 1)]));
+        assert!(registry.error_histogram("user", &json!({"id": 1, "email": "a@b.c"})).is_empty());
+        assert_eq!(registry.error_histogram("missing", &json!({})), HashMap::from([("schema".to_string(), 1)]));
+
+        let mut registry = registry;
+        registry.enable_error_samples(4);
+        registry.enable_coverage_tracking();
+        registry.error_histogram("user", &json!({"age": "old"}));
+        assert!(registry.recent_errors("user", 4).is_empty());
+        assert!(registry.coverage.as_ref().unwrap().lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_best_match() {
+        let registry = registry_with(&[
+            ("user", json!({"type": "object", "required": ["id", "email"]})),
+            ("order", json!({"type": "object", "required": ["id", "total", "items"]})),
+            ("event", json!({"type": "object", "required": ["id", "email"]})),
+        ]);
+        let data = json!({"id": 1});
+
+        let ranked = registry.best_match(&data, None);
+        assert_eq!(ranked, vec![
+            ("event".to_string(), 1),
+            ("user".to_string(), 1),
+            ("order".to_string(), 2),
+        ]);
+
+        let ranked = registry.best_match(&data, Some(&["order", "missing"]));
+        assert_eq!(ranked, vec![("order".to_string(), 2)]);
+
+        // 比對候選不應留下錯誤樣本或覆蓋率
+        let mut registry = registry;
+        registry.enable_error_samples(4);
+        registry.enable_coverage_tracking();
+        registry.best_match(&data, None);
+        assert!(registry.recent_errors("order", 4).is_empty());
+        assert!(registry.coverage.as_ref().unwrap().lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_layered_registry() {
+        let base = registry_with(&[
+            ("user", json!({"type": "object"})),
+            ("order", json!({"type": "object"})),
+        ]);
+        let prod = registry_with(&[("user", json!({"type": "object", "required": ["email"]}))]);
+        let staging = registry_with(&[("order", json!({"type": "object", "required": ["id"]}))]);
+        let mut layered = LayeredRegistry::new(
+            base,
+            vec![("prod".to_string(), prod), ("staging".to_string(), staging)],
+        );
+
+        assert_eq!(layered.layer_of("user"), Some("prod"));
+        assert!(layered.validate("user", &json!({})).is_err());
+        assert!(layered.validate("order", &json!({})).is_err());
+
+        layered.set_active(&["staging"]).unwrap();
+        assert_eq!(layered.active_overlays(), vec!["staging"]);
+        assert_eq!(layered.layer_of("user"), Some("base"));
+        assert!(layered.validate("user", &json!({})).is_ok());
+        assert!(layered.validate("order", &json!({})).is_err());
+
+        assert!(layered.set_active(&["dev"]).is_err());
+        assert_eq!(layered.layer_of("missing"), None);
+        assert!(layered.validate("missing", &json!({})).is_err());
+    }
+
+    #[test]
+    fn test_precompute_canonical() {
+        let mut registry = registry_with(&[
+            ("a", json!({"type": "object", "required": ["id"]})),
+            ("b", json!({"required": ["id"], "type": "object"})),
+            ("c", json!({"type": "string"})),
+        ]);
+        let hash_before = registry.schema_hash("a");
+        assert!(registry.sources_equal("a", "b"));
+
+        registry.precompute_canonical();
+        assert_eq!(registry.schema_hash("a"), hash_before);
+        assert_eq!(registry.schema_hash("a"), registry.schema_hash("b"));
+        assert!(registry.sources_equal("a", "b"));
+        assert!(!registry.sources_equal("a", "c"));
+        assert_eq!(
+            registry.canonical_source("b").unwrap().to_string(),
+            r#"{"required":["id"],"type":"object"}"#
+        );
+
+        registry.register_schema("b", json!({"type": "string"})).unwrap();
+        assert!(registry.sources_equal("b", "c"));
+        assert_eq!(registry.schema_hash("b"), registry.schema_hash("c"));
+
+        assert!(registry.unregister("c").is_some());
+        assert!(!registry.sources_equal("b", "c"));
+        assert_eq!(registry.schema_hash("c"), None);
+        assert!(registry.validate("c", &json!("x")).is_err());
+    }
+
+    #[test]
+    fn test_validate_with_extra_required() {
+        let registry = registry_with(&[(
+            "user",
+            json!({
+                "type": "object",
+                "properties": {"id": {"type": "integer"}, "email": {"type": "string"}, "phone": {"type": "string"}},
+                "required": ["id"]
+            }),
+        )]);
+        let data = json!({"id": 1, "email": "a@b.c"});
+
+        assert!(registry.validate("user", &json!({"id": 1})).is_ok());
+        assert!(registry.validate_with_extra_required("user", &["email"], &data).is_ok());
+        let errors = registry.validate_with_extra_required("user", &["email", "phone"], &data).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(registry.validate_with_extra_required("user", &["phone", "email"], &json!({})).is_err());
+        assert_eq!(registry.variants.lock().unwrap().len(), 2);
+
+        let errors = registry.validate_with_extra_required("user", &["nickname"], &data).unwrap_err();
+        assert_eq!(errors, vec!["Extra required field 'nickname' is not declared in schema user"]);
+    }
+
+    #[test]
+    fn test_concurrency_limited_registry() {
+        let registry = Arc::new(registry_with(&[("user", json!({"type": "object"}))]));
+
+        let limited = ConcurrencyLimitedRegistry::new(Arc::clone(&registry), 1, OverflowPolicy::Reject);
+        let permit = limited.permits.acquire();
+        assert_eq!(limited.in_flight(), 1);
+        assert_eq!(limited.validate("user", &json!({})), Err(Overloaded { limit: 1 }));
+        drop(permit);
+        assert_eq!(limited.in_flight(), 0);
+        assert_eq!(limited.validate("user", &json!({})), Ok(Ok(())));
+
+        let limited = Arc::new(ConcurrencyLimitedRegistry::new(registry, 2, OverflowPolicy::Queue));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let limited = Arc::clone(&limited);
+                std::thread::spawn(move || limited.validate("user", &json!([])))
+            })
+            .collect();
+        for handle in handles {
+            assert!(matches!(handle.join().unwrap(), Ok(Err(_))));
+        }
+        assert_eq!(limited.in_flight(), 0);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_concurrency_limit_shared_with_async() {
+        let registry = Arc::new(registry_with(&[("user", json!({"type": "object"}))]));
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+        let limited = ConcurrencyLimitedRegistry::new(Arc::clone(&registry), 1, OverflowPolicy::Reject);
+        let permit = limited.permits.acquire();
+        assert_eq!(runtime.block_on(limited.validate_async("user", &json!({}))), Err(Overloaded { limit: 1 }));
+        assert_eq!(limited.in_flight(), 1);
+        drop(permit);
+        let permit = runtime.block_on(limited.permits.acquire_async()).unwrap();
+        assert_eq!(limited.validate("user", &json!({})), Err(Overloaded { limit: 1 }));
+        drop(permit);
+        assert_eq!(limited.validate("user", &json!({})), Ok(Ok(())));
+
+        // 同步排隊的呼叫在 async 許可歸還後被喚醒
+        let limited = Arc::new(ConcurrencyLimitedRegistry::new(registry, 1, OverflowPolicy::Queue));
+        let permit = runtime.block_on(limited.permits.acquire_async()).unwrap();
+        let waiting = {
+            let limited = Arc::clone(&limited);
+            std::thread::spawn(move || limited.validate("user", &json!({})))
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(limited.in_flight(), 1);
+        drop(permit);
+        assert_eq!(waiting.join().unwrap(), Ok(Ok(())));
+        assert_eq!(limited.in_flight(), 0);
+    }
+
+    #[test]
+    fn test_schema_tags() {
+        let mut registry = registry_with(&[
+            ("invoice", json!({"type": "object", "x-tags": ["public", "billing"]})),
+            ("user", json!({"type": "object", "x-tags": ["public"]})),
+            ("audit", json!({"type": "object"})),
+        ]);
+
+        assert_eq!(registry.schemas_with_tag("public"), vec!["invoice", "user"]);
+        assert_eq!(registry.schemas_with_tag("billing"), vec!["invoice"]);
+        assert_eq!(registry.tags("invoice"), vec!["public", "billing"]);
+        assert!(registry.tags("audit").is_empty());
+
+        registry.register_schema("audit", json!({"x-tags": ["billing"]})).unwrap();
+        registry.unregister("invoice");
+        assert_eq!(registry.schemas_with_tag("billing"), vec!["audit"]);
+        assert_eq!(registry.schemas_with_tag("public"), vec!["user"]);
+    }
+
+    #[test]
+    fn test_upgrade_hint() {
+        let base = versioned_dir(&[
+            ("v1", &[("order", json!({
+                "type": "object",
+                "properties": {"id": {}, "mail": {}, "legacy_flag": {}, "customer": {"type": "object"}},
+                "required": ["id"]
+            }))]),
+            ("v2", &[("order", json!({
+                "type": "object",
+                "properties": {
+                    "id": {},
+                    "email": {"type": "string"},
+                    "currency": {"type": "string", "default": "EUR"},
+                    "customer": {"type": "object", "properties": {"tier": {"type": "integer"}}, "required": ["tier"]}
+                },
+                "required": ["id", "email", "currency"],
+                "additionalProperties": false
+            }))]),
+        ]);
+        let registry = VersionedRegistry::new(base.to_str().unwrap(), &["v1", "v2"])
+            .unwrap()
+            .with_field_rename("order", "mail", "email");
+        let data = json!({"id": 1, "mail": "a@b.c", "legacy_flag": true, "customer": {}});
+
+        let ops = registry.upgrade_hint(&data, "v1", "v2", "order");
+        assert_eq!(ops, vec![
+            PatchOp::Move { from: "/mail".to_string(), path: "/email".to_string() },
+            PatchOp::Add { path: "/currency".to_string(), value: json!("EUR") },
+            PatchOp::Remove { path: "/legacy_flag".to_string() },
+            PatchOp::Add { path: "/customer/tier".to_string(), value: json!(0) },
+        ]);
+        assert_eq!(ops[0].to_json(), json!({"op": "move", "from": "/mail", "path": "/email"}));
+        assert!(registry.upgrade_hint(&data, "v1", "v9", "order").is_empty());
+    }
+
+    #[test]
+    fn test_header_version_router() {
+        let base = versioned_dir(&[
+            ("v1", &[("user", json!({"type": "object"}))]),
+            ("v2", &[("user", json!({"type": "object", "required": ["email"]}))]),
+        ]);
+        let registry = VersionedRegistry::new(base.to_str().unwrap(), &["v1", "v2"]).unwrap();
+        let router = HeaderVersionRouter::new(&registry)
+            .alias("latest", "v2")
+            .alias("stable", "v1");
+
+        assert_eq!(router.version_for(None), Some("v2"));
+        assert_eq!(router.version_for(Some(" Stable ")), Some("v1"));
+        assert_eq!(router.version_for(Some("v1")), Some("v1"));
+        assert_eq!(router.version_for(Some("v9")), None);
+        assert!(router.validate(Some("stable"), "user", &json!({})).is_ok());
+        assert!(router.validate(Some("latest"), "user", &json!({})).is_err());
+        assert_eq!(
+            router.validate(Some("v9"), "user", &json!({})),
+            Err(vec!["Unsupported Accept-Version 'v9'".to_string()])
+        );
+
+        let router = router.fallback_to_default(true);
+        assert_eq!(router.version_for(Some("v9")), Some("v2"));
+    }
+
+    #[test]
+    fn test_recent_errors() {
+        let mut registry = registry_with(&[("user", json!({"type": "object", "required": ["id"]}))]);
+        assert!(registry.validate("user", &json!({})).is_err());
+        assert!(registry.recent_errors("user", 10).is_empty());
+
+        registry.enable_error_samples(2);
+        assert!(registry.validate("user", &json!({})).is_err());
+        assert!(registry.validate("user", &json!({"id": 1})).is_ok());
+        assert!(registry.validate("user", &json!([])).is_err());
+        assert!(registry.validate_detailed("user", &json!("x")).is_err());
+
+        let recent = registry.recent_errors("user", 10);
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].1[0].keyword, "type");
+        assert_eq!(recent[0].1[0].instance_path, "");
+        assert!(recent[0].0 >= recent[1].0);
+        assert_eq!(registry.recent_errors("user", 1).len(), 1);
+        assert!(registry.recent_errors("order", 10).is_empty());
+    }
+
+    #[test]
+    fn test_with_switch() {
+        let cases = HashMap::from([
+            ("card".to_string(), json!({"required": ["card_number"]})),
+            ("bank".to_string(), json!({"required": ["iban"]})),
+        ]);
+        let registry = registry_with(&[("payment", json!({"type": "object", "required": ["kind"]}))])
+            .with_switch("payment", "kind", cases.clone(), UnknownSwitchValue::Error);
+
+        assert!(registry.validate("payment", &json!({"kind": "card", "card_number": "4111"})).is_ok());
+        assert!(registry.validate("payment", &json!({"kind": "card", "iban": "DE89"})).is_err());
+        assert!(registry.validate("payment", &json!({"kind": "bank", "iban": "DE89"})).is_ok());
+        assert_eq!(
+            registry.validate("payment", &json!({"kind": "cash"})),
+            Err(vec!["Unknown value 'cash' for switch field 'kind' at /kind".to_string()])
+        );
+        let errors = registry.validate_detailed("payment", &json!({"kind": "bank"})).unwrap_err();
+        assert_eq!(errors[0].keyword, "required");
+
+        let registry = registry_with(&[("payment", json!({"type": "object"}))])
+            .with_switch("payment", "kind", cases, UnknownSwitchValue::FallThrough);
+        assert!(registry.validate("payment", &json!({"kind": "cash"})).is_ok());
+        assert!(registry.validate("payment", &json!({})).is_ok());
+    }
+
+    #[test]
+    fn test_to_rules() {
+        let registry = registry_with(&[(
+            "user",
+            json!({
+                "type": "object",
+                "properties": {
+                    "age": {"type": "integer", "minimum": 0, "maximum": 150},
+                    "email": {"type": "string", "pattern": "^.+@.+$"},
+                    "name": {"type": "string", "minLength": 1},
+                    "status": {"enum": ["active", "inactive"]},
+                    "address": {
+                        "type": ["object", "null"],
+                        "properties": {"zip": {"type": "string", "maxLength": 10}},
+                        "required": ["zip"]
+                    }
+                },
+                "required": ["email"]
+            }),
+        )]);
+
+        assert_eq!(registry.to_rules("user"), vec![
+            "email is required",
+            "address must be an object or null",
+            "address.zip is required",
+            "address.zip must be a string at most 10 characters long",
+            "age must be an integer between 0 and 150",
+            "email must be a string",
+            "email must match pattern ^.+@.+$",
+            "name must be a string at least 1 characters long",
+            "status must be one of [active, inactive]",
+        ]);
+        assert!(registry.to_rules("missing").is_empty());
+    }
+
+    #[test]
+    fn test_validate_with_enum_override() {
+        let registry = registry_with(&[(
+            "order",
+            json!({
+                "type": "object",
+                "properties": {
+                    "address": {"type": "object", "properties": {"country": {"enum": ["TW"]}}}
+                }
+            }),
+        )]);
+        let data = json!({"address": {"country": "JP"}});
+
+        assert!(registry.validate("order", &data).is_err());
+        let countries = [json!("TW"), json!("JP")];
+        assert!(registry.validate_with_enum_override("order", "address.country", &countries, &data).is_ok());
+        assert!(registry
+            .validate_with_enum_override("order", "address.country", &[json!("TW")], &data)
+            .is_err());
+        assert!(registry.validate_with_enum_override("order", "address.country", &countries, &data).is_ok());
+        assert_eq!(registry.variants.lock().unwrap().len(), 2);
+
+        // 參考清單持續變動時, 快取大小仍有上限
+        for i in 0..MAX_CACHED_VARIANTS * 2 {
+            let values = [json!("TW"), json!(format!("X{}", i))];
+            assert!(registry.validate_with_enum_override("order", "address.country", &values, &data).is_err());
+        }
+        assert_eq!(registry.variants.lock().unwrap().len(), MAX_CACHED_VARIANTS);
+
+        assert_eq!(
+            registry.validate_with_enum_override("order", "address.city", &countries, &data),
+            Err(vec!["Property path 'address.city' not found in schema order".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_enum_suggestion() {
+        let registry = registry_with(&[(
+            "user",
+            json!({"properties": {"status": {"enum": ["active", "inactive", "banned"]}}}),
+        )]);
+
+        let errors = registry.validate_detailed("user", &json!({"status": "activ"})).unwrap_err();
+        assert_eq!(errors[0].suggestion.as_deref(), Some("active"));
+        assert_eq!(errors[0].to_json()["suggestion"], "active");
+
+        let errors = registry.validate_detailed("user", &json!({"status": "deleted"})).unwrap_err();
+        assert_eq!(errors[0].suggestion, None);
+
+        let registry = registry_with(&[(
+            "user",
+            json!({
+                "$defs": {"status": {"enum": ["active", "inactive"]}},
+                "properties": {"status": {"$ref": "#/$defs/status"}}
+            }),
+        )]);
+        let errors = registry.validate_detailed("user", &json!({"status": "inactiv"})).unwrap_err();
+        assert_eq!(errors[0].keyword, "enum");
+        assert_eq!(errors[0].suggestion.as_deref(), Some("inactive"));
+
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_multipart_validator() {
+        let registry = registry_with(&[("metadata", json!({"type": "object", "required": ["title"]}))]);
+        let validator = MultipartValidator::new(&registry).field("metadata", "metadata");
+
+        let fields = vec![
+            ("metadata".to_string(), br#"{"title": "report"}"#.to_vec()),
+            ("file".to_string(), b"%PDF-1.7".to_vec()),
+            ("metadata".to_string(), b"{}".to_vec()),
+            ("metadata".to_string(), b"not json".to_vec()),
+        ];
+        let results: Vec<(String, ValidationResult)> = validator.validate_stream(fields).collect();
+        assert!(results[0].1.is_ok());
+        assert_eq!(results[1], ("file".to_string(), Ok(())));
+        assert!(results[2].1.is_err());
+        assert!(results[3].1.as_ref().unwrap_err()[0].starts_with("Invalid JSON in field 'metadata'"));
+
+        let validator = validator.reject_unmapped(true);
+        assert_eq!(
+            validator.validate_field("file", b"%PDF-1.7"),
+            Err(vec!["No schema mapped for field 'file'".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_lenient_enums() {
+        let schema = json!({
+            "type": "object",
+            "properties": {"status": {"enum": ["active", "inactive"]}, "plan": {"const": "pro"}},
+            "required": ["id"]
+        });
+        let strict = registry_with(&[("user", schema.clone())]);
+        let data = json!({"id": 1, "status": "paused", "plan": "team"});
+        assert!(strict.validate("user", &data).is_err());
+        let (result, warnings) = strict.validate_with_warnings("user", &data);
+        assert_eq!(result.unwrap_err().len(), 2);
+        assert!(warnings.is_empty());
+
+        let lenient = registry_with(&[("user", schema)])
+            .with_lenient_enums("user")
+            .with_cross_field_rule("user", |data| match data["id"].as_i64() {
+                Some(id) if id > 0 => Ok(()),
+                _ => Err("id must be positive".to_string()),
+            });
+        assert!(lenient.validate("user", &data).is_ok());
+        let (result, warnings) = lenient.validate_with_warnings("user", &data);
+        assert!(result.is_ok());
+        assert_eq!(warnings.iter().map(|w| w.keyword.as_str()).collect::<Vec<_>>(), vec!["const", "enum"]);
+
+        assert!(lenient.validate("user", &json!({"status": "paused"})).is_err());
+        assert!(lenient.validate("user", &json!({"id": 0, "status": "paused"})).is_err());
+
+        assert!(lenient.is_valid("user", &data));
+        assert!(!lenient.is_valid("user", &json!({"status": "paused"})));
+        assert!(lenient.validate_with_options("user", &data, &ValidateOptions::default()).is_ok());
+        let errors = lenient
+            .validate_with_options("user", &json!({"id": 0, "status": "paused"}), &ValidateOptions::default())
+            .unwrap_err();
+        assert_eq!(errors[0].keyword, "crossField");
+        assert_eq!(lenient.validate_with_deadline("user", &data, Duration::from_secs(5)).unwrap(), Ok(()));
+        let events: Vec<ValidationEvent> = lenient.validate_observable("user", &data).iter().collect();
+        assert!(matches!(events.last(), Some(ValidationEvent::Finished { error_count: 0 })));
+
+        // 所有入口對 lenient schema 的判定一致, 被接受的數據不留下錯誤樣本
+        let mut lenient = lenient;
+        lenient.enable_error_samples(4);
+        assert!(lenient.validate("user", &data).is_ok());
+        assert!(lenient.validate_detailed("user", &data).is_ok());
+        assert!(lenient.try_validate("user", &data).is_ok());
+        assert!(lenient.error_histogram("user", &data).is_empty());
+        assert!(lenient.validate_with_extra_required("user", &["status"], &data).is_ok());
+        assert!(lenient.validate_with_enum_override("user", "status", &[json!("active")], &data).is_ok());
+        assert!(lenient.validate_with_format_overrides("user", HashMap::new(), &data).is_ok());
+        assert!(lenient.recent_errors("user", 4).is_empty());
+        assert!(lenient.validate("user", &json!({"status": "paused"})).is_err());
+        assert_eq!(lenient.recent_errors("user", 4).len(), 1);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_validate_fields_par() {
+        let registry = registry_with(&[(
+            "report",
+            json!({
+                "$defs": {"section": {"type": "object", "required": ["title"]}},
+                "properties": {
+                    "summary": {"$ref": "#/$defs/section"},
+                    "details": {"$ref": "#/$defs/section"},
+                    "pages": {"type": "integer"}
+                },
+                "required": ["appendix"]
+            }),
+        )]);
+        let data = json!({"summary": {"title": "Q3"}, "details": {}, "pages": 12, "extra": true});
+
+        let results = registry.validate_fields_par("report", &data);
+        assert_eq!(results.len(), 3);
+        assert!(results["summary"].is_ok());
+        assert!(results["details"].as_ref().unwrap_err()[0].ends_with("at /details"));
+        assert!(results["pages"].is_ok());
+        assert!(registry.validate_fields_par("missing", &data)[""].is_err());
+    }
+
+    #[test]
+    fn test_debug_dump() {
+        let dir = schema_dir(&[
+            ("user", json!({"type": "object", "x-tags": ["public"]})),
+            ("broken", json!({"type": 12})),
+        ]);
+        let hot = HotReloadRegistry::new(dir.to_str().unwrap()).unwrap();
+
+        let dump = hot.debug_dump();
+        assert_eq!(dump["schema_count"], 1);
+        assert_eq!(dump["schemas"][0]["name"], "user");
+        assert_eq!(dump["schemas"][0]["tags"], json!(["public"]));
+        assert!(dump["schemas"][0]["hash"].is_string());
+        assert!(dump["schemas"][0].get("source").is_none());
+        assert_eq!(dump["schemas"][0]["draft"], format!("{:?}", Draft::default()));
+        assert!(dump["load_errors"]["broken"].is_string());
+        assert_eq!(dump["last_reload"], Value::Null);
+
+        hot.reload().unwrap();
+        let dump = hot.debug_dump();
+        assert!(dump["last_reload"].is_u64());
+        assert_eq!(dump["compile_error_history"].as_array().unwrap().len(), 2);
+        assert_eq!(dump["compile_error_history"][0]["schema"], "broken");
+        assert_eq!(hot.debug_dump_with_sources()["schemas"][0]["source"]["type"], "object");
+    }
+
+    #[test]
+    fn test_with_switch_keeps_local_refs() {
+        let cases = HashMap::from([("card".to_string(), json!({"required": ["amount"]}))]);
+        let registry = registry_with(&[(
+            "payment",
+            json!({
+                "$defs": {"money": {"type": "integer", "minimum": 0}},
+                "type": "object",
+                "properties": {"amount": {"$ref": "#/$defs/money"}},
+                "allOf": [{"required": ["kind"]}]
+            }),
+        )])
+        .with_switch("payment", "kind", cases, UnknownSwitchValue::Error);
+
+        assert!(registry.validate("payment", &json!({"kind": "card", "amount": 5})).is_ok());
+        assert!(registry.validate("payment", &json!({"kind": "card", "amount": -5})).is_err());
+        assert!(registry.validate("payment", &json!({"kind": "card"})).is_err());
+    }
+
+    #[test]
+    fn test_hot_reload_keeps_builder_options() {
+        let dir = schema_dir(&[("user", json!({"type": "object"}))]);
+        let builder = SchemaRegistry::builder()
+            .permissive_unknown_schema(true)
+            .with_source_transform(|_, schema| schema["required"] = json!(["id"]));
+        let hot = HotReloadRegistry::with_builder(dir.to_str().unwrap(), builder).unwrap();
+
+        for _ in 0..2 {
+            assert!(hot.validate("user", &json!({})).is_err());
+            assert!(hot.validate("user", &json!({"id": 1})).is_ok());
+            assert!(hot.validate("missing", &json!({})).is_ok());
+            hot.reload().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_cross_field_rules_in_every_entry_point() {
+        let registry = registry_with(&[("range", json!({
+            "type": "object",
+            "properties": {"start": {"type": "integer"}, "end": {"type": "integer"}}
+        }))])
+        .with_cross_field_rule("range", |data| {
+            if data["start"].as_i64() <= data["end"].as_i64() { Ok(()) } else { Err("start must not exceed end".to_string()) }
+        });
+        let data = json!({"start": 5, "end": 1});
+
+        assert!(!registry.is_valid("range", &data));
+        assert!(registry.is_valid("range", &json!({"start": 1, "end": 5})));
+        assert_eq!(
+            registry.validate_with_deadline("range", &data, Duration::from_secs(5)).unwrap(),
+            Err(vec!["start must not exceed end".to_string()])
+        );
+        let errors = registry.validate_with_options("range", &data, &ValidateOptions::default()).unwrap_err();
+        assert_eq!(errors[0].keyword, "crossField");
+        assert!(registry.validate_with_extra_required("range", &["start"], &data).is_err());
+        assert!(registry.validate_with_format_overrides("range", HashMap::new(), &data).is_err());
+
+        let events: Vec<ValidationEvent> = registry.validate_observable("range", &data).iter().collect();
+        assert!(matches!(events.last(), Some(ValidationEvent::Finished { error_count: 1 })));
+    }
+
+    #[test]
+    fn test_switch_in_every_entry_point() {
+        let mut cases = HashMap::new();
+        cases.insert("card".to_string(), json!({"required": ["number"]}));
+        let registry = registry_with(&[("payment", json!({"type": "object"}))])
+            .with_switch("payment", "kind", cases, UnknownSwitchValue::Error);
+        let card = json!({"kind": "card"});
+        let unknown = json!({"kind": "cash"});
+
+        assert!(!registry.is_valid("payment", &card));
+        assert!(!registry.is_valid("payment", &unknown));
+        assert!(registry.is_valid("payment", &json!({"kind": "card", "number": "4111"})));
+        for data in [&card, &unknown] {
+            assert!(registry.validate_with_deadline("payment", data, Duration::from_secs(5)).unwrap().is_err());
+            assert!(registry.validate_with_options("payment", data, &ValidateOptions::default()).is_err());
+            let events: Vec<ValidationEvent> = registry.validate_observable("payment", data).iter().collect();
+            assert!(matches!(events.last(), Some(ValidationEvent::Finished { error_count: 1 })));
+        }
+
+        let opts = ValidateOptions { numeric_epsilon: Some(0.01), ..ValidateOptions::default() };
+        let errors = registry.validate_with_options("payment", &card, &opts).unwrap_err();
+        assert_eq!(errors[0].keyword, "required");
+    }
+
+    #[test]
+    fn test_switch_in_variant_entry_points() {
+        let cases = HashMap::from([(
+            "card".to_string(),
+            json!({"required": ["number"], "properties": {"number": {"minLength": 4}}}),
+        )]);
+        let registry = registry_with(&[(
+            "payment",
+            json!({
+                "type": "object",
+                "properties": {"kind": {"type": "string"}, "number": {"type": "string"}}
+            }),
+        )])
+        .with_switch("payment", "kind", cases, UnknownSwitchValue::Error);
+        let valid = json!({"kind": "card", "number": "4111"});
+
+        for data in [json!({"kind": "card"}), json!({"kind": "cash"})] {
+            assert!(registry.validate("payment", &data).is_err());
+            assert!(registry.validate_with_extra_required("payment", &["kind"], &data).is_err());
+            assert!(registry.validate_with_enum_override("payment", "kind", &[json!("card"), json!("cash")], &data).is_err());
+            assert!(registry.validate_with_format_overrides("payment", HashMap::new(), &data).is_err());
+        }
+        assert!(registry.validate_with_extra_required("payment", &["kind"], &valid).is_ok());
+        assert!(registry.validate_with_enum_override("payment", "kind", &[json!("card")], &valid).is_ok());
+        assert!(registry.validate_with_format_overrides("payment", HashMap::new(), &valid).is_ok());
+
+        #[cfg(feature = "rayon")]
+        {
+            let results = registry.validate_fields_par("payment", &json!({"kind": "card", "number": "41"}));
+            assert!(results["kind"].is_ok());
+            assert!(results["number"].is_err());
+            assert!(registry.validate_fields_par("payment", &json!({"kind": "cash"}))[""].is_err());
+            assert!(registry.validate_fields_par("payment", &valid).values().all(Result::is_ok));
+        }
+    }
+
+    #[test]
+    fn test_validation_version_and_tracing() {
+        let base = versioned_dir(&[
+            ("v1", &[("user", json!({"type": "object"}))]),
+            ("v2", &[("user", json!({"type": "object", "required": ["email"]}))]),
+        ]);
+        let versioned = VersionedRegistry::new(base.to_str().unwrap(), &["v1", "v2"]).unwrap();
+        for version in ["v1", "v2"] {
+            assert_eq!(versioned.versions[version].version(), Some(version));
+        }
+        assert_eq!(registry_with(&[("user", json!({}))]).version(), None);
+
+        // 啟用 `otel` 時 `validate` 包在 span 中, 結果必須與未追蹤的驗證相同
+        let registry = &versioned.versions["v2"];
+        for data in [json!({"email": "a@b.c"}), json!({}), json!([])] {
+            assert_eq!(registry.validate("user", &data), registry.validate_untraced("user", &data));
+        }
+        assert_eq!(registry.validate("missing", &json!({})), registry.validate_untraced("missing", &json!({})));
+        #[cfg(feature = "otel")]
+        {
+            let errors = Err(vec!["boom".to_string()]);
+            assert_eq!(with_validation_span("user", Some("v2"), || errors.clone()), errors);
+            assert_eq!(with_validation_span("user", None, || Ok(())), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_debug_dump_reports_effective_draft() {
+        let registry = registry_with(&[
+            ("legacy", json!({"$schema": "http://json-schema.org/draft-04/schema#", "type": "object"})),
+            ("plain", json!({"type": "object"})),
+        ]);
+        assert_eq!(registry.draft("legacy"), None);
+
+        let dump = registry.debug_dump();
+        assert_eq!(dump["schemas"][0]["name"], "legacy");
+        assert_eq!(dump["schemas"][0]["draft"], "Draft4");
+        assert_eq!(dump["schemas"][1]["draft"], format!("{:?}", Draft::default()));
     }
 }